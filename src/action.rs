@@ -1,8 +1,14 @@
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 use strum::Display;
 
-type Command = String;
-type CommandArgs = Vec<String>;
+use crate::{
+    dnote::{DnoteBook, DnotePage, NoteId},
+    finder::FinderItem,
+    search::SearchHit,
+    state::Mode,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Display, Deserialize)]
 pub enum Action {
@@ -17,14 +23,20 @@ pub enum Action {
     Help,
     FocusNext,
     FocusPrev,
+    Focus(Mode),
     LoadBooks,
-    ExecuteCommand(Command, CommandArgs),
+    BooksLoaded(Vec<DnoteBook>),
     StatusLine(String),
     ClosePopup,
     SubmitPopup,
     LoadActiveBookPages,
     UpdateActiveBookPages,
     LoadActivePageContent,
+    ActiveBookPagesLoaded(String, Vec<DnotePage>),
+    ActiveBookPagesUpdated(String, Vec<DnotePage>),
+    ActivePageContentLoaded(NoteId, String),
+    ShowActivePageInfo,
+    ActivePageInfoLoaded(DnotePage),
     SelectNextBook,
     SelectPrevBook,
     SelectNextPage,
@@ -35,9 +47,144 @@ pub enum Action {
     EditActivePage,
     DeleteActivePage,
     DeleteActiveBook,
+    MoveActivePage,
+    ExportActivePages,
+    OpenFilter,
+    NextMatch,
+    PrevMatch,
+    CyclePageSort,
+    ReversePageSort,
+    ToggleMark,
+    ToggleVisualMode,
+    MarkAll,
+    ClearMarks,
+    /// Ask to confirm `message` before running `action`.
+    Confirm {
+        title: String,
+        message: String,
+        action: Box<Action>,
+    },
+    EditActivePageExternally,
+    OpenEditor(NoteDraft),
+    OpenExternalEditor(NoteDraft),
+    AddNote {
+        book: String,
+        content: String,
+    },
+    EditNote {
+        id: NoteId,
+        content: String,
+    },
+    MoveNote {
+        id: NoteId,
+        book: String,
+    },
+    RenameBook {
+        from: String,
+        to: String,
+    },
+    DeleteNote(NoteId),
+    DeleteBook(String),
+    MoveNotes {
+        ids: Vec<NoteId>,
+        book: String,
+    },
+    DeleteNotes(Vec<NoteId>),
+    ExportNotes {
+        ids: Vec<NoteId>,
+        path: PathBuf,
+    },
+    NotesExported {
+        count: usize,
+        path: PathBuf,
+    },
+    NotesChanged {
+        message: String,
+        book: Option<String>,
+    },
+    HardRefresh,
+    Sync,
+    SyncCompleted,
+    SyncFailed(String),
+    OpenSearch,
+    SearchResultsLoaded(String, Vec<SearchHit>),
+    JumpToNote {
+        book: String,
+        id: NoteId,
+    },
+    JumpToBook(String),
+    OpenFinder,
+    FinderItemsLoaded(Vec<FinderItem>),
+    FinderPreviewLoaded(NoteId, String),
+    OpenTrash,
+    /// Delete trash entry `id` for good.
+    PurgeTrash(String),
+    ToggleMarkdown,
+    ScrollDown,
+    ScrollUp,
+    ScrollHalfPageDown,
+    ScrollHalfPageUp,
+    ScrollToTop,
+    ScrollToBottom,
+    ToggleWrap,
+}
+
+/// A note being written, either a new note for `book` or an edit of note `id`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NoteDraft {
+    pub book: String,
+    pub id: Option<NoteId>,
+    /// The content the editor starts with.
+    pub content: String,
+}
+
+impl NoteDraft {
+    /// Draft a new note for `book`.
+    pub fn new_note(book: String) -> Self {
+        Self {
+            book,
+            ..Default::default()
+        }
+    }
+
+    /// Draft an edit of the content of note `id` in `book`.
+    pub fn edit_note(book: String, id: NoteId, content: String) -> Self {
+        Self {
+            book,
+            id: Some(id),
+            content,
+        }
+    }
+
+    /// The mutation saving `content` for this draft, or `None` if an existing note is unchanged.
+    pub fn save(self, content: String) -> Option<Action> {
+        match self.id {
+            None => Some(Action::AddNote {
+                book: self.book,
+                content,
+            }),
+            Some(_) if content.trim_end() == self.content.trim_end() => None,
+            Some(id) => Some(Action::EditNote { id, content }),
+        }
+    }
 }
 
 impl Action {
+    /// Whether this action changes notes or books in the store.
+    pub fn is_mutation(&self) -> bool {
+        matches!(
+            self,
+            Action::AddNote { .. }
+                | Action::EditNote { .. }
+                | Action::MoveNote { .. }
+                | Action::RenameBook { .. }
+                | Action::DeleteNote(_)
+                | Action::DeleteBook(_)
+                | Action::MoveNotes { .. }
+                | Action::DeleteNotes(_)
+        )
+    }
+
     pub fn label(&self) -> &'static str {
         match self {
             Action::Quit => "Quit",
@@ -56,8 +203,34 @@ pub fn label(&self) -> &'static str {
             Action::AddBook => "Add",
             Action::AddPageToActiveBook => "Add",
             Action::EditActivePage => "Edit",
+            Action::EditActivePageExternally => "Edit Externally",
+            Action::ShowActivePageInfo => "Info",
+            Action::MoveActivePage => "Move",
+            Action::ExportActivePages => "Export",
+            Action::OpenFilter => "Filter",
+            Action::NextMatch => "Next Match",
+            Action::PrevMatch => "Prev Match",
+            Action::CyclePageSort => "Sort",
+            Action::ReversePageSort => "Reverse Sort",
+            Action::ToggleMark => "Mark",
+            Action::ToggleVisualMode => "Visual",
+            Action::MarkAll => "Mark All",
+            Action::ClearMarks => "Unmark",
             Action::DeleteActivePage => "Delete",
             Action::DeleteActiveBook => "Delete",
+            Action::OpenTrash => "Trash",
+            Action::Sync => "Sync",
+            Action::HardRefresh => "Reload",
+            Action::OpenSearch => "Search",
+            Action::OpenFinder => "Find",
+            Action::ToggleMarkdown => "Raw/Markdown",
+            Action::ScrollDown => "Down",
+            Action::ScrollUp => "Up",
+            Action::ScrollHalfPageDown => "Half Page Down",
+            Action::ScrollHalfPageUp => "Half Page Up",
+            Action::ScrollToTop => "Top",
+            Action::ScrollToBottom => "Bottom",
+            Action::ToggleWrap => "Wrap",
             _ => "",
         }
     }
@@ -76,8 +249,34 @@ pub fn order(&self) -> usize {
             Action::RenameActiveBook => 45,
             Action::AddPageToActiveBook => 40,
             Action::EditActivePage => 50,
-            Action::DeleteActivePage => 60,
-            Action::DeleteActiveBook => 60,
+            Action::EditActivePageExternally => 51,
+            Action::ShowActivePageInfo => 55,
+            Action::MoveActivePage => 56,
+            Action::OpenFilter => 25,
+            Action::NextMatch => 26,
+            Action::PrevMatch => 27,
+            Action::CyclePageSort => 64,
+            Action::ReversePageSort => 65,
+            Action::ToggleMark => 57,
+            Action::ToggleVisualMode => 58,
+            Action::MarkAll => 59,
+            Action::ClearMarks => 60,
+            Action::ExportActivePages => 63,
+            Action::DeleteActivePage => 61,
+            Action::DeleteActiveBook => 61,
+            Action::OpenTrash => 62,
+            Action::Sync => 70,
+            Action::OpenSearch => 66,
+            Action::OpenFinder => 67,
+            Action::ToggleMarkdown => 68,
+            Action::ToggleWrap => 69,
+            Action::ScrollDown => 10,
+            Action::ScrollUp => 20,
+            Action::ScrollHalfPageDown => 21,
+            Action::ScrollHalfPageUp => 22,
+            Action::ScrollToTop => 23,
+            Action::ScrollToBottom => 24,
+            Action::HardRefresh => 75,
             _ => 100,
         }
     }
@@ -1,34 +1,41 @@
-use std::process::Command;
+use std::{io::Write, process::Command};
 
-use color_eyre::eyre::Result;
-use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
-use crossterm::{event::KeyEvent, ExecutableCommand};
+use chrono::{DateTime, Local, Utc};
+use color_eyre::eyre::{eyre, Result};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Position, Size},
     prelude::Rect,
+    Frame,
 };
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
 use crate::{
-    action::Action,
+    action::{Action, NoteDraft},
     components::{
+        book_picker::BookPicker,
         books::BooksPane,
         content::ContentPane,
+        finder::FinderPopup,
         footer::FooterPane,
+        format_relative_time,
         header::HeaderPane,
         pages::PagesPane,
         popup::{Popup, PopupType},
+        search::SearchPopup,
+        trash::TrashPopup,
         Component,
     },
     config::Config,
-    dnote::Dnote,
-    state::{InputMode, Mode, State},
-    tui,
+    dnote::DnotePage,
+    state::{InputMode, Loading, Mode, State},
+    store::{self, count_notes, SharedStore},
+    trash::Trash,
+    tui::{self, Screen},
 };
 
 pub struct App {
     pub config: Config,
-    pub tui: tui::Tui,
     pub action_tx: UnboundedSender<Action>,
     pub action_rx: UnboundedReceiver<Action>,
     pub tick_rate: f64,
@@ -40,25 +47,54 @@ pub struct App {
     pub should_quit: bool,
     pub should_suspend: bool,
     pub last_tick_key_events: Vec<KeyEvent>,
-    pub dnote: Dnote,
+    pub store: SharedStore,
     pub state: State,
+    /// Size of the screen, as of the last resize.
+    pub size: Size,
+    /// Size of the open popup, shrunk to fit the screen when drawn.
+    pub popup_size: Size,
+    /// Mode to return to when the open popup closes, however it is closed.
+    pub popup_return_mode: Mode,
+    /// Copies of deleted notes and books, taken before each delete.
+    pub trash: Trash,
+    /// Areas the books, pages and content panes were last drawn in, to find the pane under the
+    /// mouse.
+    pub pane_areas: [Rect; 3],
+    /// Area the open popup was last drawn in.
+    pub popup_area: Option<Rect>,
 }
 
+/// Modes focused by clicking each pane, in the order of `App::components`.
+const PANE_MODES: [Mode; 3] = [Mode::Book, Mode::Page, Mode::Content];
+
+/// Size of the input, info and error popups.
+const POPUP_SIZE: Size = Size::new(50, 10);
+/// Size of the search popup, which lists results under its input.
+const SEARCH_POPUP_SIZE: Size = Size::new(100, 24);
+/// Size of the finder popup, which previews the selected note beside its matches.
+const FINDER_POPUP_SIZE: Size = Size::new(120, 28);
+/// Size of the book picker popup, which lists books under its input.
+const PICKER_POPUP_SIZE: Size = Size::new(60, 20);
+/// Size of the trash popup, which previews the selected entry beside the list.
+const TRASH_POPUP_SIZE: Size = Size::new(110, 24);
+
 impl App {
-    pub fn new(tick_rate: f64, frame_rate: f64) -> Result<Self> {
-        let tui = tui::Tui::new()?.tick_rate(tick_rate).frame_rate(frame_rate);
+    pub fn new(
+        tick_rate: f64,
+        frame_rate: f64,
+        config: Config,
+        store: SharedStore,
+    ) -> Result<Self> {
         let (action_tx, action_rx) = mpsc::unbounded_channel();
         let mut state = State::new();
         state.mode = Mode::Book;
-        let dnote = Dnote::new();
         let header = HeaderPane::default();
         let footer = FooterPane::default();
         let books = BooksPane::default();
         let pages = PagesPane::default();
         let content = ContentPane::default();
-        let config = Config::new()?;
+        let trash = Trash::new(config.config.data_dir.join("trash"));
         let app = Self {
-            tui,
             action_tx,
             action_rx,
             tick_rate,
@@ -76,61 +112,149 @@ pub fn new(tick_rate: f64, frame_rate: f64) -> Result<Self> {
             should_suspend: false,
             config,
             last_tick_key_events: Vec::new(),
-            dnote,
+            store,
             state,
+            size: Size::default(),
+            popup_size: POPUP_SIZE,
+            popup_return_mode: Mode::Book,
+            trash,
+            pane_areas: [Rect::default(); 3],
+            popup_area: None,
         };
         Ok(app)
     }
 
-    fn wait_for_enter_to_return(&self) -> Result<()> {
-        std::io::stdout()
-            .execute(SetForegroundColor(Color::Green))?
-            .execute(Print("\nPress enter to return to dnote-tui"))?
-            .execute(ResetColor)?;
-        use std::io::{self, BufRead};
-        let stdin = io::stdin();
-        stdin.lock().lines().next();
-        Ok(())
-    }
+    /// Open `content` in the user's editor (the `editor` config key, `$VISUAL`, `$EDITOR` or else
+    /// `vi`), handing the screen over while it runs. Returns the edited content, or `None` if it
+    /// was left empty.
+    fn edit_externally<S: Screen>(&self, screen: &mut S, content: &str) -> Result<Option<String>> {
+        let editor = self
+            .config
+            .config
+            .editor
+            .clone()
+            .or_else(|| {
+                ["VISUAL", "EDITOR"]
+                    .into_iter()
+                    .find_map(|key| std::env::var(key).ok())
+            })
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| "vi".to_string());
+        // Editors are often configured with arguments, e.g. `code --wait`
+        let mut args = shlex::split(&editor)
+            .filter(|args| !args.is_empty())
+            .ok_or_else(|| eyre!("Unable to parse editor command `{}`", editor))?;
+        let program = args.remove(0);
+        let mut file = tempfile::Builder::new()
+            .prefix("dnote-tui-")
+            .suffix(".md")
+            .tempfile()?;
+        file.write_all(content.as_bytes())?;
+        file.flush()?;
 
-    fn log_command(&self, command: &str, args: &[&str]) -> Result<()> {
-        std::io::stdout()
-            .execute(SetForegroundColor(Color::Blue))?
-            .execute(Print(format!("\n=> {} {}\n\n", command, args.join(" "))))?
-            .execute(ResetColor)?;
-        Ok(())
+        screen.release()?;
+        let status = Command::new(&program).args(&args).arg(file.path()).status();
+        screen.restore()?;
+        let status = status.map_err(|err| eyre!("Failed to run editor `{}`: {}", program, err))?;
+        if !status.success() {
+            return Err(eyre!("Editor `{}` exited with {}", program, status));
+        }
+        let edited = std::fs::read_to_string(file.path())?;
+        let edited = edited.trim_end().to_string();
+        Ok(Some(edited).filter(|c| !c.trim().is_empty()))
     }
 
-    fn spawn_process(&self, command: &str, args: &[&str]) -> Result<()> {
-        self.log_command(command, args)?;
-        let status = Command::new(command).args(args).status()?;
-        if !status.success() {
-            eprintln!("\nCommand failed with status: {}", status);
+    /// Write `draft` in the external editor and save it unless it was left empty or unchanged.
+    fn edit_draft_externally<S: Screen>(&mut self, screen: &mut S, draft: NoteDraft) -> Result<()> {
+        match self.edit_externally(screen, &draft.content) {
+            Ok(Some(content)) => match draft.save(content) {
+                Some(mutation) => self.action_tx.send(mutation)?,
+                None => log::info!("Note unchanged, not saving it"),
+            },
+            Ok(None) => log::info!("Empty note, not saving it"),
+            Err(err) => self.action_tx.send(Action::Error(err.to_string()))?,
         }
-        self.wait_for_enter_to_return()?;
         Ok(())
     }
 
-    pub fn resume(&mut self) -> Result<()> {
-        self.tui.enter()?;
-        self.tui.clear()?;
-        Ok(())
+    /// Show a `size` popup on top of the panes, taking over key events until it is closed.
+    fn open_popup(&mut self, popup: impl Component + 'static, size: Size) -> Result<()> {
+        self.open_popup_in(popup, size, None)
     }
 
-    pub fn pause(&mut self) -> Result<()> {
-        self.tui.exit()?;
+    /// Like [`Self::open_popup`], switching to `mode`, if any, until the popup is closed.
+    fn open_popup_in(
+        &mut self,
+        popup: impl Component + 'static,
+        size: Size,
+        mode: Option<Mode>,
+    ) -> Result<()> {
+        let mut popup: Box<dyn Component> = Box::new(popup);
+        popup.register_action_handler(self.action_tx.clone())?;
+        popup.register_config_handler(self.config.clone())?;
+        popup.register_store_handler(self.store.clone())?;
+        popup.init(self.size)?;
+        // A popup replacing another returns to where the first one was opened from
+        if self.popup.replace(popup).is_none() {
+            self.popup_return_mode = self.state.mode;
+        }
+        self.state.mode = mode.unwrap_or(self.popup_return_mode);
+        self.popup_size = size;
+        self.state.input_mode = InputMode::Insert;
         Ok(())
     }
 
+    /// Close the popup, if any, returning to the mode it was opened from.
     pub fn close_popup(&mut self) -> Result<()> {
-        self.popup.take();
+        if self.popup.take().is_some() {
+            self.state.mode = self.popup_return_mode;
+        }
+        self.state.input_mode = InputMode::Normal;
         Ok(())
     }
 
-    pub async fn run(&mut self) -> Result<()> {
-        // tui.mouse(true);
-        self.tui.enter()?;
+    /// Hand `mouse` to the popup or pane under it. Clicking a pane focuses it, and clicking
+    /// outside the popup cancels it.
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        let position = Position::new(mouse.column, mouse.row);
+        let clicked = mouse.kind == MouseEventKind::Down(MouseButton::Left);
+        if let Some(popup) = &mut self.popup {
+            match self.popup_area {
+                Some(area) if area.contains(position) => {
+                    if let Some(action) = popup.handle_mouse_events(mouse, &mut self.state)? {
+                        self.action_tx.send(action)?;
+                    }
+                }
+                Some(_) if clicked => self.action_tx.send(Action::ClosePopup)?,
+                _ => {}
+            }
+            return Ok(());
+        }
+        // The built-in editor and the filter lines keep the keyboard, and the mouse, to themselves
+        if self.state.input_mode == InputMode::Insert {
+            return Ok(());
+        }
+        let Some(index) = self.pane_areas.iter().position(|a| a.contains(position)) else {
+            return Ok(());
+        };
+        if let Some(action) = self.components[index].handle_mouse_events(mouse, &mut self.state)? {
+            self.action_tx.send(action)?;
+        }
+        // Like moving focus with the keyboard, a pane with nothing to show can't be focused
+        let focusable = match PANE_MODES[index] {
+            Mode::Page => self.state.get_active_book().is_some(),
+            Mode::Content => self.state.get_active_page().is_some(),
+            _ => true,
+        };
+        if clicked && focusable {
+            self.action_tx.send(Action::Focus(PANE_MODES[index]))?;
+        }
+        Ok(())
+    }
 
+    /// Register the handlers of all components and initialize them for a screen of `size`.
+    pub fn init(&mut self, size: Size) -> Result<()> {
+        self.size = size;
         for component in self.components.iter_mut() {
             component.register_action_handler(self.action_tx.clone())?;
         }
@@ -140,7 +264,11 @@ pub async fn run(&mut self) -> Result<()> {
         }
 
         for component in self.components.iter_mut() {
-            component.init(self.tui.size()?)?;
+            component.register_store_handler(self.store.clone())?;
+        }
+
+        for component in self.components.iter_mut() {
+            component.init(size)?;
         }
 
         self.header
@@ -151,265 +279,460 @@ pub async fn run(&mut self) -> Result<()> {
         self.header.register_config_handler(self.config.clone())?;
         self.footer.register_config_handler(self.config.clone())?;
 
-        self.header.init(self.tui.size()?)?;
-        self.footer.init(self.tui.size()?)?;
+        self.header.init(size)?;
+        self.footer.init(size)?;
 
         if let Some(popup) = &mut self.popup {
             popup.register_action_handler(self.action_tx.clone())?;
             popup.register_config_handler(self.config.clone())?;
-            popup.init(self.tui.size()?)?;
+            popup.init(size)?;
         }
+        Ok(())
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let mut tui = tui::Tui::new()?
+            .tick_rate(self.tick_rate)
+            .frame_rate(self.frame_rate)
+            .mouse(self.config.config.mouse);
+        tui.enter()?;
+        self.init(tui.size()?)?;
 
         loop {
-            if let Some(e) = self.tui.next_event().await {
-                match e {
-                    tui::Event::Quit if self.state.input_mode == InputMode::Normal => {
-                        self.action_tx.send(Action::Quit)?
-                    }
-                    tui::Event::Tick => self.action_tx.send(Action::Tick)?,
-                    tui::Event::Render => self.action_tx.send(Action::Render)?,
-                    tui::Event::Resize(x, y) => self.action_tx.send(Action::Resize(x, y))?,
-                    tui::Event::Key(key) => {
-                        match self.state.input_mode {
-                            InputMode::Normal => {
-                                if let Some(keymap) = self.config.keybindings.get(&self.state.mode)
-                                {
-                                    if let Some(action) = keymap.get(&vec![key]) {
-                                        log::info!("Got action: {action:?}");
-                                        self.action_tx.send(action.clone())?;
-                                    } else {
-                                        // If the key was not handled as a single key action,
-                                        // then consider it for multi-key combinations.
-                                        self.last_tick_key_events.push(key);
-
-                                        // Check for multi-key combinations
-                                        if let Some(action) = keymap.get(&self.last_tick_key_events)
-                                        {
-                                            log::info!("Got action: {action:?}");
-                                            self.action_tx.send(action.clone())?;
-                                        }
-                                    }
-                                };
-                            }
-                            InputMode::Insert => {
-                                log::debug!("Skipping keybinds from config in insert mode...");
+            if let Some(e) = tui.next_event().await {
+                self.handle_event(e)?;
+            }
+            self.handle_actions(&mut tui)?;
+            if self.should_suspend {
+                tui.suspend()?;
+                self.action_tx.send(Action::Resume)?;
+                self.action_tx.send(Action::Refresh)?;
+                tui.enter()?;
+                tui.clear()?;
+            } else if self.should_quit {
+                tui.stop()?;
+                break;
+            }
+        }
+        tui.exit()?;
+        Ok(())
+    }
+
+    /// Map an event to the actions it triggers, through the keybindings and the components.
+    pub fn handle_event(&mut self, e: tui::Event) -> Result<()> {
+        if let tui::Event::Mouse(mouse) = e {
+            return self.handle_mouse(mouse);
+        }
+        match e {
+            tui::Event::Quit if self.state.input_mode == InputMode::Normal => {
+                self.action_tx.send(Action::Quit)?
+            }
+            tui::Event::Tick => self.action_tx.send(Action::Tick)?,
+            tui::Event::Render => self.action_tx.send(Action::Render)?,
+            tui::Event::Resize(x, y) => self.action_tx.send(Action::Resize(x, y))?,
+            tui::Event::Key(key) => {
+                match self.state.input_mode {
+                    InputMode::Normal => {
+                        if let Some(keymap) = self.config.keybindings.get(&self.state.mode) {
+                            if let Some(action) = keymap.get(&vec![key]) {
+                                log::info!("Got action: {action:?}");
+                                self.action_tx.send(action.clone())?;
+                            } else {
+                                // If the key was not handled as a single key action,
+                                // then consider it for multi-key combinations.
+                                self.last_tick_key_events.push(key);
+
+                                // Check for multi-key combinations
+                                if let Some(action) = keymap.get(&self.last_tick_key_events) {
+                                    log::info!("Got action: {action:?}");
+                                    self.action_tx.send(action.clone())?;
+                                    self.last_tick_key_events.clear();
+                                }
                             }
-                        }
+                        };
                     }
-                    _ => {}
-                }
-                for component in self.components.iter_mut() {
-                    if let Some(action) =
-                        component.handle_events(Some(e.clone()), &mut self.state)?
-                    {
-                        self.action_tx.send(action)?;
+                    InputMode::Insert => {
+                        log::debug!("Skipping keybinds from config in insert mode...");
                     }
                 }
-                if let Some(popup) = &mut self.popup {
-                    if let Some(action) = popup.handle_events(Some(e.clone()), &mut self.state)? {
-                        self.action_tx.send(action)?
-                    };
-                }
-                if let Some(action) = self
-                    .header
-                    .handle_events(Some(e.clone()), &mut self.state)?
-                {
-                    self.action_tx.send(action)?
-                };
-                if let Some(action) = self
-                    .footer
-                    .handle_events(Some(e.clone()), &mut self.state)?
-                {
-                    self.action_tx.send(action)?
-                };
             }
+            _ => {}
+        }
+        for component in self.components.iter_mut() {
+            if let Some(action) = component.handle_events(Some(e.clone()), &mut self.state)? {
+                self.action_tx.send(action)?;
+            }
+        }
+        if let Some(popup) = &mut self.popup {
+            if let Some(action) = popup.handle_events(Some(e.clone()), &mut self.state)? {
+                self.action_tx.send(action)?
+            };
+        }
+        if let Some(action) = self
+            .header
+            .handle_events(Some(e.clone()), &mut self.state)?
+        {
+            self.action_tx.send(action)?
+        };
+        if let Some(action) = self
+            .footer
+            .handle_events(Some(e.clone()), &mut self.state)?
+        {
+            self.action_tx.send(action)?
+        };
+        Ok(())
+    }
 
-            while let Ok(action) = self.action_rx.try_recv() {
-                if action != Action::Tick && action != Action::Render {
-                    log::info!("{action:?}");
+    /// Process all pending actions, drawing to `screen` when asked to render.
+    pub fn handle_actions<S: Screen>(&mut self, screen: &mut S) -> Result<()> {
+        while let Ok(action) = self.action_rx.try_recv() {
+            if action != Action::Tick && action != Action::Render {
+                log::info!("{action:?}");
+            }
+            match action {
+                Action::Tick => {
+                    self.last_tick_key_events.drain(..);
                 }
-                match action {
-                    Action::Tick => {
-                        self.last_tick_key_events.drain(..);
-                    }
-                    Action::Quit if self.state.input_mode == InputMode::Normal => {
-                        self.should_quit = true
-                    }
-                    Action::Suspend => self.should_suspend = true,
-                    Action::Resume => self.should_suspend = false,
-                    Action::Refresh => self.tui.terminal.clear()?,
-                    Action::Resize(w, h) => {
-                        self.tui.resize(Rect::new(0, 0, w, h))?;
-                        self.draw()?;
-                    }
-                    Action::Render => {
-                        self.draw()?;
-                    }
-                    Action::ExecuteCommand(ref command, ref args) => {
-                        self.pause()?;
-                        let cmd = command.to_string();
-                        let cmd_args = args.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
-                        self.spawn_process(&cmd, &cmd_args)?;
-                        self.resume()?;
-                    }
-                    Action::AddBook => {
+                Action::Quit if self.state.input_mode == InputMode::Normal => {
+                    self.should_quit = true
+                }
+                Action::Focus(mode) => self.state.mode = mode,
+                Action::Suspend => self.should_suspend = true,
+                Action::Resume => self.should_suspend = false,
+                Action::Refresh => screen.clear()?,
+                Action::Resize(w, h) => {
+                    self.size = Size::new(w, h);
+                    screen.resize(Rect::new(0, 0, w, h))?;
+                    self.draw(screen)?;
+                }
+                Action::Render => {
+                    self.draw(screen)?;
+                }
+                Action::Error(_) => {
+                    // A failed store call never delivers its result, stop waiting for it. Syncs
+                    // report their own failures.
+                    self.state.loading = Loading {
+                        sync: self.state.loading.sync,
+                        ..Default::default()
+                    };
+                }
+                Action::OpenEditor(ref draft) if self.config.config.external_editor => {
+                    self.edit_draft_externally(screen, draft.clone())?;
+                }
+                Action::OpenExternalEditor(ref draft) => {
+                    self.edit_draft_externally(screen, draft.clone())?;
+                }
+                ref action if action.is_mutation() => {
+                    let action = action.clone();
+                    let trash = self.trash.clone();
+                    let tx = self.action_tx.clone();
+                    store::spawn_task(self.store.clone(), self.action_tx.clone(), move |store| {
+                        let trashed = trash.snapshot(store, &action)?;
+                        let bulk =
+                            matches!(action, Action::MoveNotes { .. } | Action::DeleteNotes(_));
+                        let result = store::apply_mutation(store, action);
+                        if result.is_err() {
+                            if let Err(err) = trash.forget_undeleted(store, &trashed) {
+                                log::error!(
+                                    "Failed to drop trash entries of a failed delete: {err}"
+                                );
+                            }
+                        }
+                        match result {
+                            // Some of the notes may have changed before the failure, show them
+                            Err(err) if bulk => {
+                                let _ = tx.send(Action::Error(err.to_string()));
+                                Ok(Action::LoadBooks)
+                            }
+                            result => result,
+                        }
+                    });
+                }
+                Action::NotesChanged { ref book, .. } => {
+                    self.state.pending_book.clone_from(book);
+                    self.action_tx.send(Action::LoadBooks)?;
+                }
+                Action::AddBook => {
+                    let input_popup = Popup::new(
+                        "Add New Book".into(),
+                        "Name".into(),
+                        "".into(),
+                        Some("Note: Book names cannot contain spaces!".into()),
+                        PopupType::NewBook,
+                    );
+                    self.open_popup(input_popup, POPUP_SIZE)?;
+                }
+                Action::RenameActiveBook => {
+                    if let Some(ref book) = self.state.get_active_book() {
                         let input_popup = Popup::new(
-                            "Add New Book".into(),
+                            "Rename Book".into(),
                             "Name".into(),
-                            "".into(),
+                            book.name.clone(),
                             Some("Note: Book names cannot contain spaces!".into()),
-                            PopupType::NewBook,
+                            PopupType::RenameBook,
                         );
-                        self.popup = Some(Box::new(input_popup));
-                        if let Some(popup) = &mut self.popup {
-                            popup.register_action_handler(self.action_tx.clone())?;
-                            popup.register_config_handler(self.config.clone())?;
-                            popup.init(self.tui.size()?)?;
-                        }
-                        self.state.input_mode = InputMode::Insert;
+                        self.open_popup(input_popup, POPUP_SIZE)?;
+                    } else {
+                        log::error!("No active book to rename");
                     }
-                    Action::RenameActiveBook => {
-                        if let Some(ref book) = self.state.get_active_book() {
-                            let input_popup = Popup::new(
-                                "Rename Book".into(),
-                                "Name".into(),
-                                book.name.clone(),
-                                Some("Note: Book names cannot contain spaces!".into()),
-                                PopupType::RenameBook,
-                            );
-                            self.popup = Some(Box::new(input_popup));
-                            if let Some(popup) = &mut self.popup {
-                                popup.register_action_handler(self.action_tx.clone())?;
-                                popup.register_config_handler(self.config.clone())?;
-                                popup.init(self.tui.size()?)?;
+                }
+                Action::Confirm {
+                    ref title,
+                    ref message,
+                    ref action,
+                } => {
+                    let confirm_popup =
+                        Popup::confirm(title.clone(), message.clone(), *action.clone());
+                    self.open_popup(confirm_popup, POPUP_SIZE)?;
+                }
+                Action::MoveActivePage => {
+                    let pages = self.state.get_target_pages();
+                    if let (Some(book), Some(page)) = (self.state.get_active_book(), pages.first())
+                    {
+                        // Books hidden by the filter are still places to move to
+                        let books = self
+                            .state
+                            .books
+                            .all_items()
+                            .iter()
+                            .filter(|b| b.name != book.name)
+                            .map(|b| b.name.clone())
+                            .collect();
+                        let picker = match pages.len() {
+                            1 => {
+                                let id = page.id;
+                                BookPicker::new(
+                                    format!("Move Note {} From {}", id, book.name),
+                                    books,
+                                    move |book| Action::MoveNote { id, book },
+                                )
                             }
-                            self.state.input_mode = InputMode::Insert;
-                        } else {
-                            log::error!("No active book to rename");
-                        }
+                            n => {
+                                let ids: Vec<_> = pages.iter().map(|page| page.id).collect();
+                                BookPicker::new(
+                                    format!("Move {n} Notes From {}", book.name),
+                                    books,
+                                    move |book| Action::MoveNotes {
+                                        ids: ids.clone(),
+                                        book,
+                                    },
+                                )
+                            }
+                        };
+                        self.open_popup(picker, PICKER_POPUP_SIZE)?;
+                    } else {
+                        log::error!("No active page to move");
                     }
-                    Action::SubmitPopup => {
-                        self.popup.take(); // set popup to None
-                        self.state.input_mode = InputMode::Normal;
+                }
+                Action::ExportActivePages => {
+                    let pages = self.state.get_target_pages();
+                    if let Some(book) = self.state.get_active_book().filter(|_| !pages.is_empty()) {
+                        let export_popup = Popup::new(
+                            format!("Export {}", count_notes(pages.len())),
+                            "Path".into(),
+                            format!("{}.md", book.name),
+                            Some("Note: Relative paths start from the current directory".into()),
+                            PopupType::ExportNotes(pages.iter().map(|page| page.id).collect()),
+                        );
+                        self.open_popup(export_popup, POPUP_SIZE)?;
+                    } else {
+                        log::error!("No active page to export");
                     }
-                    Action::ClosePopup => {
-                        self.popup.take(); // set popup to None
-                        self.state.input_mode = InputMode::Normal;
+                }
+                Action::ExportNotes { ref ids, ref path } => {
+                    let (ids, path) = (ids.clone(), path.clone());
+                    store::spawn_task(self.store.clone(), self.action_tx.clone(), move |store| {
+                        store::export_notes(store, &ids, &path)
+                    });
+                }
+                Action::OpenSearch => {
+                    self.open_popup_in(
+                        SearchPopup::default(),
+                        SEARCH_POPUP_SIZE,
+                        Some(Mode::Search),
+                    )?;
+                }
+                Action::OpenFinder => {
+                    self.open_popup(FinderPopup::new(), FINDER_POPUP_SIZE)?;
+                }
+                Action::OpenTrash => {
+                    self.open_popup(TrashPopup::new(self.trash.clone()), TRASH_POPUP_SIZE)?;
+                }
+                Action::PurgeTrash(ref id) => {
+                    if let Err(err) = self.trash.purge(id) {
+                        self.action_tx.send(Action::Error(err.to_string()))?;
                     }
-                    _ => {}
+                    self.action_tx.send(Action::OpenTrash)?;
                 }
-                for component in self.components.iter_mut() {
-                    if let Some(action) = component.update(action.clone(), &mut self.state)? {
-                        self.action_tx.send(action)?
-                    };
+                Action::HardRefresh => {
+                    store::spawn_task(self.store.clone(), self.action_tx.clone(), |store| {
+                        store.clear_cache()?;
+                        Ok(Action::LoadBooks)
+                    });
                 }
-                if let Some(popup) = &mut self.popup {
-                    if let Some(action) = popup.update(action.clone(), &mut self.state)? {
-                        self.action_tx.send(action)?
-                    };
+                Action::Sync if !self.state.loading.sync => {
+                    self.state.loading.sync = true;
+                    store::spawn_task(self.store.clone(), self.action_tx.clone(), |store| {
+                        Ok(match store.sync() {
+                            Ok(()) => Action::SyncCompleted,
+                            Err(err) => Action::SyncFailed(err.to_string()),
+                        })
+                    });
+                }
+                Action::SyncCompleted => {
+                    self.state.loading.sync = false;
+                    self.action_tx.send(Action::LoadBooks)?;
                 }
-                if let Some(action) = self.header.update(action.clone(), &mut self.state)? {
+                Action::SyncFailed(ref message) => {
+                    self.state.loading.sync = false;
+                    let error_popup = Popup::error("Sync Failed".into(), message.clone());
+                    self.open_popup(error_popup, POPUP_SIZE)?;
+                }
+                Action::ActivePageInfoLoaded(ref page) => {
+                    let info_popup =
+                        Popup::info(format!("Note {}", page.id), note_info_fields(page));
+                    self.open_popup(info_popup, POPUP_SIZE)?;
+                }
+                Action::SubmitPopup | Action::ClosePopup => self.close_popup()?,
+                _ => {}
+            }
+            for component in self.components.iter_mut() {
+                if let Some(action) = component.update(action.clone(), &mut self.state)? {
                     self.action_tx.send(action)?
                 };
-                if let Some(action) = self.footer.update(action.clone(), &mut self.state)? {
+            }
+            if let Some(popup) = &mut self.popup {
+                if let Some(action) = popup.update(action.clone(), &mut self.state)? {
                     self.action_tx.send(action)?
                 };
             }
-            if self.should_suspend {
-                self.tui.suspend()?;
-                self.action_tx.send(Action::Resume)?;
-                self.action_tx.send(Action::Refresh)?;
-                // tui.mouse(true);
-                self.tui.enter()?;
-                self.tui.clear()?;
-            } else if self.should_quit {
-                self.tui.stop()?;
-                break;
-            }
+            if let Some(action) = self.header.update(action.clone(), &mut self.state)? {
+                self.action_tx.send(action)?
+            };
+            if let Some(action) = self.footer.update(action.clone(), &mut self.state)? {
+                self.action_tx.send(action)?
+            };
         }
-        self.tui.exit()?;
         Ok(())
     }
 
-    fn draw(&mut self) -> Result<()> {
-        self.tui.draw(|f| {
-            let vertical_layout = Layout::vertical(vec![
-                Constraint::Max(3),
-                Constraint::Fill(1),
-                Constraint::Max(1),
-            ])
+    pub fn draw<S: Screen>(&mut self, screen: &mut S) -> Result<()> {
+        screen.draw_frame(&mut |f| self.render(f))
+    }
+
+    /// Render the header, the panes, the popup if any and the footer.
+    pub fn render(&mut self, f: &mut Frame<'_>) {
+        let vertical_layout = Layout::vertical(vec![
+            Constraint::Max(3),
+            Constraint::Fill(1),
+            Constraint::Max(1),
+        ])
+        .horizontal_margin(1)
+        .split(f.area());
+
+        let header_chunk = vertical_layout[0];
+        let main_chunk = vertical_layout[1];
+        let footer_chunk = vertical_layout[2];
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
             .horizontal_margin(1)
-            .split(f.area());
+            .constraints(
+                [
+                    Constraint::Percentage(15),
+                    Constraint::Percentage(35),
+                    Constraint::Percentage(50),
+                ]
+                .as_ref(),
+            )
+            .split(main_chunk);
+        self.pane_areas = [chunks[0], chunks[1], chunks[2]];
 
-            let header_chunk = vertical_layout[0];
-            let main_chunk = vertical_layout[1];
-            let footer_chunk = vertical_layout[2];
-
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .horizontal_margin(1)
-                .constraints(
-                    [
-                        Constraint::Percentage(15),
-                        Constraint::Percentage(35),
-                        Constraint::Percentage(50),
-                    ]
-                    .as_ref(),
-                )
-                .split(main_chunk);
-
-            self.header
-                .draw(f, header_chunk, &mut self.state)
+        self.header
+            .draw(f, header_chunk, &mut self.state)
+            .unwrap_or_else(|err| {
+                self.action_tx
+                    .send(Action::Error(format!("Failed to draw header: {:?}", err)))
+                    .unwrap();
+            });
+
+        for (index, component) in self.components.iter_mut().enumerate() {
+            component
+                .draw(f, chunks[index], &mut self.state)
                 .unwrap_or_else(|err| {
                     self.action_tx
-                        .send(Action::Error(format!("Failed to draw header: {:?}", err)))
+                        .send(Action::Error(format!(
+                            "Failed to draw component: {:?}",
+                            err
+                        )))
                         .unwrap();
                 });
+        }
 
-            for (index, component) in self.components.iter_mut().enumerate() {
-                component
-                    .draw(f, chunks[index], &mut self.state)
-                    .unwrap_or_else(|err| {
-                        self.action_tx
-                            .send(Action::Error(format!(
-                                "Failed to draw component: {:?}",
-                                err
-                            )))
-                            .unwrap();
-                    });
-            }
+        self.popup_area = None;
+        if let Some(popup) = &mut self.popup {
+            let popup_vertical_layout = Layout::vertical(vec![
+                Constraint::Min(1),
+                Constraint::Length(self.popup_size.height),
+                Constraint::Min(1),
+            ])
+            .split(f.area());
 
-            if let Some(popup) = &mut self.popup {
-                let popup_vertical_layout = Layout::vertical(vec![
-                    Constraint::Min(1),
-                    Constraint::Length(10),
-                    Constraint::Min(1),
-                ])
-                .split(f.area());
-
-                let popup_layout = Layout::horizontal(vec![
-                    Constraint::Min(3),
-                    Constraint::Length(50),
-                    Constraint::Min(3),
-                ])
-                .split(popup_vertical_layout[1]);
-
-                popup
-                    .draw(f, popup_layout[1], &mut self.state)
-                    .unwrap_or_else(|err| {
-                        self.action_tx
-                            .send(Action::Error(format!("Failed to draw popup: {:?}", err)))
-                            .unwrap();
-                    });
-            }
-            self.footer
-                .draw(f, footer_chunk, &mut self.state)
+            let popup_layout = Layout::horizontal(vec![
+                Constraint::Min(3),
+                Constraint::Length(self.popup_size.width),
+                Constraint::Min(3),
+            ])
+            .split(popup_vertical_layout[1]);
+            self.popup_area = Some(popup_layout[1]);
+
+            popup
+                .draw(f, popup_layout[1], &mut self.state)
                 .unwrap_or_else(|err| {
                     self.action_tx
-                        .send(Action::Error(format!("Failed to draw footer: {:?}", err)))
+                        .send(Action::Error(format!("Failed to draw popup: {:?}", err)))
                         .unwrap();
                 });
-        })?;
-        Ok(())
+        }
+        self.footer
+            .draw(f, footer_chunk, &mut self.state)
+            .unwrap_or_else(|err| {
+                self.action_tx
+                    .send(Action::Error(format!("Failed to draw footer: {:?}", err)))
+                    .unwrap();
+            });
     }
 }
+
+/// Fields shown in the note info popup. Metadata the backend doesn't provide is shown as unknown.
+fn note_info_fields(page: &DnotePage) -> Vec<(String, String)> {
+    const UNKNOWN: &str = "unknown";
+    let now = Utc::now();
+    let format_time = |time: Option<DateTime<Utc>>| match time {
+        Some(time) => format!(
+            "{} ({})",
+            time.with_timezone(&Local).format("%b %-d, %Y %-I:%M%P"),
+            format_relative_time(time, now)
+        ),
+        None => UNKNOWN.to_string(),
+    };
+    vec![
+        (
+            "Book".into(),
+            page.book_name.clone().unwrap_or(UNKNOWN.into()),
+        ),
+        ("Id".into(), page.id.to_string()),
+        ("UUID".into(), page.uuid.clone().unwrap_or(UNKNOWN.into())),
+        ("Added".into(), format_time(page.added_on)),
+        ("Edited".into(), format_time(page.edited_on)),
+        (
+            "Public".into(),
+            match page.public {
+                Some(true) => "yes".into(),
+                Some(false) => "no".into(),
+                None => UNKNOWN.into(),
+            },
+        ),
+        ("Summary".into(), page.summary.clone()),
+    ]
+}
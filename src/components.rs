@@ -1,19 +1,34 @@
-use color_eyre::Result;
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::eyre, Result};
 use crossterm::event::{KeyEvent, MouseEvent};
 use ratatui::{
-    layout::{Rect, Size},
+    layout::{Position, Rect, Size},
+    style::Stylize,
+    text::Line,
     Frame,
 };
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::{action::Action, config::Config, state::State, tui::Event};
+use crate::{
+    action::Action,
+    config::Config,
+    state::{State, StatefulList},
+    store::{self, NoteStore, SharedStore},
+    tui::Event,
+};
 
+pub mod book_picker;
 pub mod books;
 pub mod content;
+pub mod editor;
+pub mod filter;
+pub mod finder;
 pub mod footer;
 pub mod header;
 pub mod pages;
 pub mod popup;
+pub mod search;
+pub mod trash;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 ///
@@ -46,6 +61,19 @@ fn register_config_handler(&mut self, config: Config) -> Result<()> {
         let _ = config; // to appease clippy
         Ok(())
     }
+    /// Register the note store that books and notes are read from and written to if necessary.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - A note store shared with the app and the other components.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An Ok result or an error.
+    fn register_store_handler(&mut self, store: SharedStore) -> Result<()> {
+        let _ = store; // to appease clippy
+        Ok(())
+    }
     /// Initialize the component with a specified area if necessary.
     ///
     /// # Arguments
@@ -134,3 +162,106 @@ fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>
     /// * `Result<()>` - An Ok result or an error.
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<()>;
 }
+
+/// Run `task` against a component's registered `store` in the background, sending the action it
+/// returns to `tx`, see [`store::spawn_task`].
+pub fn spawn_store_task<F>(
+    store: &Option<SharedStore>,
+    tx: &Option<UnboundedSender<Action>>,
+    task: F,
+) -> Result<()>
+where
+    F: FnOnce(&dyn NoteStore) -> Result<Action> + Send + 'static,
+{
+    let store = store
+        .clone()
+        .ok_or_else(|| eyre!("No note store registered"))?;
+    if let Some(tx) = tx {
+        store::spawn_task(store, tx.clone(), task);
+    }
+    Ok(())
+}
+
+/// Title shown next to a pane's title while its data is being loaded.
+pub fn loading_title(loading: bool) -> Line<'static> {
+    match loading {
+        true => Line::from(" loading… ".dark_gray().italic()),
+        false => Line::default(),
+    }
+}
+
+/// Bottom title counting the items a list's filter kept, e.g. ` filtered: 2 of 5 `.
+pub fn filtered_title<T>(list: &StatefulList<T>) -> Line<'static> {
+    match list.filter {
+        Some(_) => Line::from(format!(
+            " filtered: {} of {} ",
+            list.items.len(),
+            list.total_len()
+        )),
+        None => Line::default(),
+    }
+    .left_aligned()
+}
+
+/// Index of the item of `list` shown at `position`, if any, given the `area` it was drawn in.
+pub fn list_item_at<T>(list: &StatefulList<T>, area: Rect, position: Position) -> Option<usize> {
+    if !area.contains(position) {
+        return None;
+    }
+    let index = list.state.offset() + (position.y - area.y) as usize;
+    (index < list.items.len()).then_some(index)
+}
+
+/// Formats how long before `now` the given time was, e.g. `3d ago`.
+pub fn format_relative_time(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+    const MONTH: i64 = 30 * DAY;
+    const YEAR: i64 = 365 * DAY;
+    match (now - time).num_seconds().max(0) {
+        s if s < MINUTE => "just now".to_string(),
+        s if s < HOUR => format!("{}m ago", s / MINUTE),
+        s if s < DAY => format!("{}h ago", s / HOUR),
+        s if s < MONTH => format!("{}d ago", s / DAY),
+        s if s < YEAR => format!("{}mo ago", s / MONTH),
+        s => format!("{}y ago", s / YEAR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn list_item_at_maps_rows_to_items_past_the_offset() {
+        let mut list = StatefulList::with_items(vec!["a", "b", "c", "d"]);
+        *list.state.offset_mut() = 1;
+        let area = Rect::new(2, 5, 10, 2);
+        assert_eq!(list_item_at(&list, area, Position::new(2, 5)), Some(1));
+        assert_eq!(list_item_at(&list, area, Position::new(11, 6)), Some(2));
+        assert_eq!(list_item_at(&list, area, Position::new(12, 6)), None);
+        assert_eq!(list_item_at(&list, area, Position::new(2, 7)), None);
+        list.items.truncate(2);
+        assert_eq!(list_item_at(&list, area, Position::new(2, 6)), None);
+    }
+
+    #[test]
+    fn format_relative_time_picks_largest_unit() {
+        let now = Utc::now();
+        let cases = [
+            (Duration::seconds(5), "just now"),
+            (Duration::minutes(5), "5m ago"),
+            (Duration::hours(3), "3h ago"),
+            (Duration::days(3), "3d ago"),
+            (Duration::days(65), "2mo ago"),
+            (Duration::days(800), "2y ago"),
+            (Duration::days(-1), "just now"),
+        ];
+        for (ago, expected) in cases {
+            assert_eq!(format_relative_time(now - ago, now), expected);
+        }
+    }
+}
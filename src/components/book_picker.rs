@@ -0,0 +1,240 @@
+use color_eyre::eyre::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::*,
+    symbols::border,
+    widgets::{Block, Borders, Clear, List, ListItem, Padding, Paragraph},
+};
+use tokio::sync::mpsc::UnboundedSender;
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+
+use super::{Component, Frame};
+use crate::{
+    action::Action,
+    config::Config,
+    state::{State, StatefulList},
+    store,
+};
+
+const STATUS_LINE: &str = "Type to filter books or name a new one  ↑/↓ Select  ⏎ Pick  Esc Cancel";
+
+/// Popup picking a book among `books`, or a new one typed in, for the action built by `on_pick`.
+pub struct BookPicker {
+    title: String,
+    books: Vec<String>,
+    input: Input,
+    /// Books matching the input, followed by the input itself if it names a new book.
+    matches: StatefulList<String>,
+    error: Option<String>,
+    on_pick: Box<dyn Fn(String) -> Action>,
+    command_tx: Option<UnboundedSender<Action>>,
+    config: Config,
+}
+
+impl BookPicker {
+    pub fn new(
+        title: String,
+        books: Vec<String>,
+        on_pick: impl Fn(String) -> Action + 'static,
+    ) -> Self {
+        let mut picker = Self {
+            title,
+            books,
+            input: Input::default(),
+            matches: StatefulList::default(),
+            error: None,
+            on_pick: Box::new(on_pick),
+            command_tx: None,
+            config: Config::default(),
+        };
+        picker.filter();
+        picker
+    }
+
+    fn send_action(&self, action: Action) -> Result<()> {
+        if let Some(tx) = &self.command_tx {
+            tx.send(action.clone())?;
+        }
+        Ok(())
+    }
+
+    fn is_new_book(&self, book: &str) -> bool {
+        !self.books.iter().any(|b| b == book)
+    }
+
+    /// Narrow the books to those containing the input, ignoring case, selecting the first one.
+    fn filter(&mut self) {
+        let query = self.input.value().trim();
+        let needle = query.to_lowercase();
+        let mut matches: Vec<String> = self
+            .books
+            .iter()
+            .filter(|book| book.to_lowercase().contains(&needle))
+            .cloned()
+            .collect();
+        if !query.is_empty() && self.is_new_book(query) {
+            matches.push(query.to_string());
+        }
+        self.matches = StatefulList::with_items(matches);
+        self.matches.next();
+    }
+
+    fn pick(&mut self) -> Result<()> {
+        let Some(book) = self
+            .matches
+            .state
+            .selected()
+            .and_then(|i| self.matches.items.get(i))
+            .cloned()
+        else {
+            return Ok(());
+        };
+        if let Err(err) = store::validate_book_name(&book) {
+            self.error = Some(err.to_string());
+            return Ok(());
+        }
+        self.send_action(Action::ClosePopup)?;
+        self.send_action((self.on_pick)(book))
+    }
+}
+
+impl Component for BookPicker {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Ok(Some(Action::ClosePopup)),
+            KeyCode::Enter => self.pick()?,
+            KeyCode::Down => self.matches.next(),
+            KeyCode::Char('n') if ctrl => self.matches.next(),
+            KeyCode::Up => self.matches.previous(),
+            KeyCode::Char('p') if ctrl => self.matches.previous(),
+            _ => {
+                let changed = self
+                    .input
+                    .handle_event(&Event::Key(key))
+                    .is_some_and(|change| change.value);
+                if changed {
+                    self.error = None;
+                    self.filter();
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action, _state: &mut State) -> Result<Option<Action>> {
+        if action == Action::Tick {
+            self.send_action(Action::StatusLine(STATUS_LINE.to_string()))?;
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect, _state: &mut State) -> Result<()> {
+        f.render_widget(Clear, area);
+
+        let outer_block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(border::ROUNDED)
+            .title_top(format!("> {} <", self.title))
+            .title_alignment(Alignment::Center)
+            .padding(Padding::horizontal(1))
+            .style(Style::default().blue());
+        f.render_widget(outer_block.clone(), area);
+        let inner_area = outer_block.inner(area);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(3), // Input field
+            Constraint::Fill(1),   // Books
+            Constraint::Length(1), // Bottom text
+        ])
+        .split(inner_area);
+
+        let width = chunks[0].width.max(3) - 3; // keep 2 for borders and 1 for cursor
+        let scroll = self.input.visual_scroll(width as usize);
+        let input = Paragraph::new(self.input.value())
+            .style(Style::default().yellow())
+            .scroll((0, scroll as u16))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(border::ROUNDED)
+                    .title("Book"),
+            );
+        f.render_widget(input, chunks[0]);
+
+        let items: Vec<ListItem> = self
+            .matches
+            .items
+            .iter()
+            .map(|book| match self.is_new_book(book) {
+                true => ListItem::new(Line::from(vec![
+                    Span::styled(book.clone(), Style::default().white()),
+                    Span::styled(" (new book)", Style::default().dark_gray()),
+                ])),
+                false => ListItem::new(Span::styled(book.clone(), Style::default().white())),
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::TOP))
+            .highlight_style(Style::default().on_black().bold());
+        f.render_stateful_widget(list, chunks[1], &mut self.matches.state);
+
+        let bottom_text = match &self.error {
+            Some(error) => Paragraph::new(error.as_str()).style(Style::default().red()),
+            None => Paragraph::new("Press Esc to cancel, Enter to pick the book")
+                .style(Style::default().dark_gray()),
+        }
+        .alignment(Alignment::Center);
+        f.render_widget(bottom_text, chunks[2]);
+
+        f.set_cursor_position(Position {
+            x: chunks[0].x + ((self.input.visual_cursor()).max(scroll) - scroll) as u16 + 1,
+            y: chunks[0].y + 1,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn picker() -> BookPicker {
+        let books = vec!["docker".into(), "golang".into(), "rust".into()];
+        BookPicker::new("Move".into(), books, Action::DeleteBook)
+    }
+
+    fn type_str(picker: &mut BookPicker, s: &str) {
+        let mut state = State::default();
+        for c in s.chars() {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            picker.handle_key_events(key, &mut state).unwrap();
+        }
+    }
+
+    #[test]
+    fn book_picker_filters_books_and_offers_new_ones() {
+        let mut picker = picker();
+        assert_eq!(picker.matches.items, vec!["docker", "golang", "rust"]);
+        type_str(&mut picker, "G");
+        assert_eq!(picker.matches.items, vec!["golang", "G"]);
+        assert_eq!(picker.matches.state.selected(), Some(0));
+
+        let mut picker = self::picker();
+        type_str(&mut picker, "golang");
+        assert_eq!(picker.matches.items, vec!["golang"]);
+    }
+}
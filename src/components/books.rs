@@ -1,24 +1,38 @@
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::Result;
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{
     prelude::*,
     symbols::border,
     widgets::{block::Title, *},
 };
 use tokio::sync::mpsc::UnboundedSender;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use super::{Component, Frame};
+use super::{
+    filter::{self, FilterEvent, FilterLine},
+    filtered_title, format_relative_time, list_item_at, loading_title, spawn_store_task, Component,
+    Frame,
+};
 use crate::{
-    action::Action,
-    config::{build_status_line, Config},
-    dnote::Dnote,
-    state::{Mode, State, StatefulList},
+    action::{Action, NoteDraft},
+    config::{build_status_line, BookColumn, Config},
+    dnote::DnoteBook,
+    state::{InputMode, Mode, State, StatefulList},
+    store::{self, SharedStore},
 };
 
+/// Narrowest a book name gets before columns are dropped to make room for it.
+const MIN_NAME_WIDTH: usize = 6;
+
 #[derive(Default)]
 pub struct BooksPane {
-    dnote: Dnote,
+    store: Option<SharedStore>,
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
+    filter: FilterLine,
+    /// Where the list was last drawn, to find the book under the mouse.
+    list_area: Rect,
 }
 
 impl BooksPane {
@@ -44,6 +58,37 @@ fn send_action(&self, action: Action) -> Result<()> {
         }
         Ok(())
     }
+
+    /// Load the pages of the active book if `before` was another one, e.g. after filtering.
+    fn active_book_changed(&self, state: &mut State, before: Option<DnoteBook>) -> Result<()> {
+        let name = |book: &Option<DnoteBook>| book.as_ref().map(|b| b.name.clone());
+        match state.get_active_book() {
+            book if name(&book) == name(&before) => {}
+            Some(_) => self.send_action(Action::LoadActiveBookPages)?,
+            None => {
+                state.pages = StatefulList::default();
+                state.page_content = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Select `book` and load its pages, reloading books first if it isn't listed yet.
+    fn jump_to_book(&self, state: &mut State, book: String) -> Result<()> {
+        if state.books.all_items().iter().any(|b| b.name == book) {
+            // Jumping shows the book even when the filter hides it
+            if !state.books.items.iter().any(|b| b.name == book) {
+                state.books.clear_filter();
+            }
+            state.select_book(DnoteBook::new(book));
+            self.send_action(Action::LoadActiveBookPages)?;
+        } else {
+            // The book is newer than the loaded books, e.g. it was synced since
+            state.pending_book = Some(book);
+            self.send_action(Action::LoadBooks)?;
+        }
+        Ok(())
+    }
 }
 
 impl Component for BooksPane {
@@ -62,26 +107,113 @@ fn register_config_handler(&mut self, config: Config) -> Result<()> {
         Ok(())
     }
 
+    fn register_store_handler(&mut self, store: SharedStore) -> Result<()> {
+        self.store = Some(store);
+        Ok(())
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent, state: &mut State) -> Result<Option<Action>> {
+        if !self.filter.is_open() {
+            return Ok(None);
+        }
+        let before = state.get_active_book();
+        match self.filter.handle_key(key) {
+            FilterEvent::Continue => {}
+            FilterEvent::Changed(query) => state.books.filter(&query, book_matches),
+            FilterEvent::Keep if state.books.filter_query() != Some("") => {
+                state.input_mode = InputMode::Normal;
+            }
+            FilterEvent::Keep | FilterEvent::Clear => {
+                state.input_mode = InputMode::Normal;
+                state.books.clear_filter();
+            }
+        }
+        self.active_book_changed(state, before)?;
+        Ok(None)
+    }
+
+    fn handle_mouse_events(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let position = Position::new(mouse.column, mouse.row);
+                if let Some(index) = list_item_at(&state.books, self.list_area, position) {
+                    let before = state.get_active_book();
+                    state.books.state.select(Some(index));
+                    self.active_book_changed(state, before)?;
+                }
+                Ok(None)
+            }
+            MouseEventKind::ScrollDown => Ok(Some(Action::SelectNextBook)),
+            MouseEventKind::ScrollUp => Ok(Some(Action::SelectPrevBook)),
+            _ => Ok(None),
+        }
+    }
+
     fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
         match action {
-            Action::Tick => {
-                if self.is_focused(state) {
-                    let status_line = self.get_status_line();
-                    self.send_action(Action::StatusLine(status_line))?;
-                }
+            Action::Tick if self.filter.is_open() => {
+                self.send_action(Action::StatusLine(filter::STATUS_LINE.to_string()))?;
+            }
+            Action::Tick if self.is_focused(state) => {
+                let status_line = self.get_status_line();
+                self.send_action(Action::StatusLine(status_line))?;
             }
             Action::Render => {}
-            Action::FocusNext => {
-                // Change to page pane
-                if state.books.state.selected().is_some() {
-                    state.mode = Mode::Page;
-                    self.send_action(Action::SelectNextPage)?;
-                }
+            // Change to page pane
+            Action::FocusNext
+                if self.is_focused(state) && state.books.state.selected().is_some() =>
+            {
+                self.send_action(Action::Focus(Mode::Page))?;
+                self.send_action(Action::SelectNextPage)?;
             }
             Action::FocusPrev => {}
             Action::LoadBooks => {
-                let books = self.dnote.get_books()?;
-                state.books = StatefulList::with_items(books);
+                state.loading.books = true;
+                spawn_store_task(&self.store, &self.command_tx, |store| {
+                    Ok(Action::BooksLoaded(store.get_books()?))
+                })?;
+            }
+            Action::BooksLoaded(books) => {
+                state.loading.books = false;
+                // Reloads (e.g. after a sync) keep the active book and refresh its pages, unless
+                // a change asked for another book to be selected
+                let selected = match state.pending_book.take() {
+                    Some(name) => {
+                        state.books = StatefulList::with_items(books);
+                        state.select_book(DnoteBook::new(name));
+                        state.books.state.selected().is_some()
+                    }
+                    None => state
+                        .books
+                        .replace_items_by(books, |book| book.name.clone()),
+                };
+                if selected {
+                    self.send_action(Action::LoadActiveBookPages)?;
+                } else {
+                    // The active book is gone, e.g. it was deleted
+                    state.pages = StatefulList::default();
+                    state.page_content = None;
+                }
+            }
+            Action::OpenFilter if self.is_focused(state) => {
+                let query = state.books.filter_query().unwrap_or_default().to_string();
+                state.books.filter(&query, book_matches);
+                self.filter.open(&query);
+                state.input_mode = InputMode::Insert;
+            }
+            Action::NextMatch | Action::PrevMatch
+                if self.is_focused(state) && state.books.filter.is_some() =>
+            {
+                let before = state.get_active_book();
+                match action {
+                    Action::NextMatch => state.books.next_wrapping(),
+                    _ => state.books.previous_wrapping(),
+                }
+                self.active_book_changed(state, before)?;
             }
             Action::SelectNextBook => {
                 state.select_next_book();
@@ -91,23 +223,35 @@ fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>
                 state.select_prev_book();
                 self.send_action(Action::LoadActiveBookPages)?;
             }
+            Action::JumpToBook(book) => {
+                state.mode = Mode::Book;
+                self.jump_to_book(state, book)?;
+            }
+            Action::JumpToNote { book, id } => {
+                state.pending_page = Some(id);
+                state.mode = Mode::Page;
+                self.jump_to_book(state, book)?;
+            }
             Action::AddPageToActiveBook => {
                 if let Some(book) = state.get_active_book() {
-                    let cmd = String::from("dnote");
-                    let cmd_args = vec!["add".into(), book.name.clone()];
-                    self.send_action(Action::ExecuteCommand(cmd, cmd_args))?;
-                    self.send_action(Action::UpdateActiveBookPages)?;
-                    self.send_action(Action::LoadActivePageContent)?;
+                    self.send_action(Action::OpenEditor(NoteDraft::new_note(book.name)))?;
                 } else {
                     log::error!("No active book to add page to");
                 }
             }
             Action::DeleteActiveBook => {
                 if let Some(book) = state.get_active_book() {
-                    let cmd = String::from("dnote");
-                    let cmd_args = vec!["remove".into(), book.name.clone()];
-                    self.send_action(Action::ExecuteCommand(cmd, cmd_args))?;
-                    self.send_action(Action::LoadBooks)?;
+                    spawn_store_task(&self.store, &self.command_tx, move |store| {
+                        let notes = match store.get_pages(&book.name)?.len() {
+                            1 => "its note".to_string(),
+                            n => format!("all {n} of its notes"),
+                        };
+                        Ok(Action::Confirm {
+                            title: "Delete Book".into(),
+                            message: format!("Delete book {} and {notes}?", book.name),
+                            action: Box::new(Action::DeleteBook(book.name)),
+                        })
+                    })?;
                 } else {
                     log::error!("No active book to delete");
                 }
@@ -118,20 +262,23 @@ fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>
     }
 
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<()> {
-        let items: Vec<ListItem> = state
-            .books
-            .items
-            .iter()
-            .map(|i| ListItem::new(i.name.clone()))
-            .collect();
-        let total_items = items.len();
+        let total_items = state.books.items.len();
         let current_item_index = match state.books.state.selected() {
             Some(v) => v + 1,
             None => 0,
         };
         let title = Title::from(" Books ".blue().bold());
+        let title_loading = loading_title(state.loading.books);
+        // Shorter than the other panes' ` 1 of 2 `, leaving room for the totals
         let title_bottom =
-            Line::from(format!(" {} of {} ", current_item_index, total_items)).right_aligned();
+            Line::from(format!(" {}/{} ", current_item_index, total_items)).right_aligned();
+        // The totals make way for the filter's count while filtering
+        let title_counts = match state.books.filter {
+            Some(_) => filtered_title(&state.books),
+            None => totals_title(state.books.all_items())
+                .filter(|totals| totals.width() + title_bottom.width() + 2 <= area.width as usize)
+                .unwrap_or_default(),
+        };
         let title_padding = Line::from("");
         let border_style = match self.is_focused(state) {
             true => Style::default().blue(),
@@ -143,15 +290,204 @@ fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<(
             .style(border_style)
             .title(title_padding.clone().left_aligned())
             .title(title)
+            .title(title_loading)
+            .title_bottom(title_counts.blue())
             .title_bottom(title_bottom.blue().bold())
             .title_bottom(title_padding.clone().right_aligned());
         let highlight_style = Style::default().on_black().bold();
+        let list_area = self
+            .filter
+            .draw(f, block.inner(area), state.books.filter_query());
+        self.list_area = list_area;
+        f.render_widget(block, area);
+        let items = book_rows(
+            &state.books.items,
+            &self.config.config.book_columns,
+            list_area.width as usize,
+            Utc::now(),
+        );
         let list = List::new(items)
-            .block(block)
             .style(Style::default().white())
             // .highlight_symbol("→ ")
             .highlight_style(highlight_style);
-        f.render_stateful_widget(list, area, &mut state.books.state);
+        f.render_stateful_widget(list, list_area, &mut state.books.state);
         Ok(())
     }
 }
+
+/// Whether `book` matches a lowercased filter `query`.
+fn book_matches(book: &DnoteBook, query: &str) -> bool {
+    book.name.to_lowercase().contains(query)
+}
+
+/// Bottom title totalling the notes of `books`, e.g. ` 42 notes `, if their counts are known.
+fn totals_title(books: &[DnoteBook]) -> Option<Line<'static>> {
+    let counts: Option<Vec<usize>> = books.iter().map(|book| book.note_count).collect();
+    let total = counts.filter(|_| !books.is_empty())?.iter().sum();
+    Some(Line::from(format!(" {} ", store::count_notes(total))).left_aligned())
+}
+
+/// Rows of the books list, `width` columns wide: each name, truncated with an ellipsis, then
+/// the configured `columns` right-aligned. Columns no book has data for are left out, and those
+/// squeezing names below [`MIN_NAME_WIDTH`] are dropped from the right.
+fn book_rows(
+    books: &[DnoteBook],
+    columns: &[BookColumn],
+    width: usize,
+    now: DateTime<Utc>,
+) -> Vec<ListItem<'static>> {
+    let columns: Vec<BookColumn> = columns
+        .iter()
+        .copied()
+        .filter(|column| {
+            books
+                .iter()
+                .any(|book| book_cell(book, *column, now).is_some())
+        })
+        .collect();
+    let cells: Vec<Vec<String>> = books
+        .iter()
+        .map(|book| {
+            columns
+                .iter()
+                .map(|column| book_cell(book, *column, now).unwrap_or_else(|| "–".into()))
+                .collect()
+        })
+        .collect();
+    let mut widths: Vec<usize> = (0..columns.len())
+        .map(|i| cells.iter().map(|row| row[i].width()).max().unwrap_or(0))
+        .collect();
+    // Every column is preceded by a space
+    let columns_width = |widths: &[usize]| widths.iter().map(|w| w + 1).sum::<usize>();
+    while columns_width(&widths) + MIN_NAME_WIDTH > width && widths.pop().is_some() {}
+    let name_width = width.saturating_sub(columns_width(&widths));
+    books
+        .iter()
+        .zip(cells)
+        .map(|(book, cells)| {
+            let name = truncate(&book.name, name_width);
+            let padding = " ".repeat(name_width - name.width());
+            let mut spans = vec![Span::raw(name + &padding)];
+            for (cell, width) in cells.into_iter().zip(&widths) {
+                let padding = " ".repeat(width - cell.width());
+                spans.push(Span::raw(format!(" {padding}{cell}")).dark_gray());
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect()
+}
+
+/// Text of `column` for `book`, if the backend provides it.
+fn book_cell(book: &DnoteBook, column: BookColumn, now: DateTime<Utc>) -> Option<String> {
+    match column {
+        BookColumn::Notes => book.note_count.map(|count| count.to_string()),
+        // Shortened to fit the narrow pane, e.g. `3d` rather than `3d ago`
+        BookColumn::Edited => {
+            book.last_edited
+                .map(|time| match format_relative_time(time, now).as_str() {
+                    "just now" => "now".to_string(),
+                    ago => ago.trim_end_matches(" ago").to_string(),
+                })
+        }
+    }
+}
+
+/// `text` cut to `width` columns, ending with an ellipsis if it was cut.
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += w;
+    }
+    if width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn book(name: &str, note_count: usize, edited_days_ago: i64, now: DateTime<Utc>) -> DnoteBook {
+        DnoteBook {
+            note_count: Some(note_count),
+            last_edited: Some(now - Duration::days(edited_days_ago)),
+            ..DnoteBook::new(name)
+        }
+    }
+
+    fn rendered(rows: Vec<ListItem<'static>>, width: u16) -> Vec<String> {
+        let height = rows.len() as u16;
+        let mut buffer = Buffer::empty(Rect::new(0, 0, width, height));
+        Widget::render(List::new(rows), buffer.area, &mut buffer);
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn book_rows_align_columns_and_drop_them_when_narrow() {
+        let now = Utc::now();
+        let books = vec![
+            book("rust", 12, 3, now),
+            book("DockerEssentials", 1, 65, now),
+        ];
+        let columns = [BookColumn::Notes, BookColumn::Edited];
+        assert_eq!(
+            rendered(book_rows(&books, &columns, 24, now), 24),
+            vec!["rust              12  3d", "DockerEssentials   1 2mo",]
+        );
+        assert_eq!(
+            rendered(book_rows(&books, &columns, 16, now), 16),
+            vec!["rust      12  3d", "DockerEs…  1 2mo"]
+        );
+        assert_eq!(
+            rendered(book_rows(&books, &columns, 12, now), 12),
+            vec!["rust      12", "DockerEs…  1"]
+        );
+        assert_eq!(
+            rendered(book_rows(&books, &columns, 7, now), 7),
+            vec!["rust   ", "Docker…"]
+        );
+    }
+
+    #[test]
+    fn book_rows_skip_columns_the_backend_does_not_provide() {
+        let now = Utc::now();
+        let books = vec![DnoteBook {
+            note_count: Some(2),
+            ..DnoteBook::new("rust")
+        }];
+        let columns = [BookColumn::Edited, BookColumn::Notes];
+        assert_eq!(
+            rendered(book_rows(&books, &columns, 10, now), 10),
+            vec!["rust     2"]
+        );
+        assert_eq!(totals_title(&books).unwrap().to_string(), " 2 notes ");
+        assert_eq!(totals_title(&[DnoteBook::new("rust")]), None);
+    }
+
+    #[test]
+    fn truncate_ends_with_an_ellipsis_by_display_width() {
+        assert_eq!(truncate("rust", 4), "rust");
+        assert_eq!(truncate("notes-日本語", 9), "notes-日…");
+        assert_eq!(truncate("notes-日本語", 8), "notes-…");
+        assert_eq!(truncate("rust", 0), "");
+    }
+}
@@ -1,4 +1,5 @@
 use color_eyre::eyre::Result;
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
 use ratatui::{
     prelude::*,
     symbols::border,
@@ -6,20 +7,86 @@
 };
 use tokio::sync::mpsc::UnboundedSender;
 
-use super::{Component, Frame};
-use crate::{action::Action, config::Config, dnote::Dnote, state::State};
+use super::{
+    editor::{self, EditorEvent, NoteEditor},
+    loading_title, spawn_store_task, Component, Frame,
+};
+use crate::{
+    action::Action,
+    config::{build_status_line, Config},
+    dnote::NoteId,
+    markdown::{self, MarkdownStyles},
+    state::{InputMode, Mode, State},
+    store::SharedStore,
+};
 
 #[derive(Default)]
 pub struct ContentPane {
-    dnote: Dnote,
+    store: Option<SharedStore>,
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
+    /// The built-in editor, while a note is being written.
+    editor: Option<NoteEditor>,
+    /// Show notes as written instead of rendering their markdown.
+    raw: bool,
+    markdown: MarkdownStyles,
+    /// The last rendered note, with the width it was rendered for.
+    rendered: Option<(String, u16, Text<'static>)>,
+    /// The note shown, its scroll offset is reset when another one is.
+    note: Option<NoteId>,
+    /// Lines scrolled past the top of the pane.
+    scroll: u16,
+    /// Largest scroll offset that still fills the pane, as of the last draw.
+    max_scroll: u16,
+    /// Height of the pane as of the last draw, for half page scrolling.
+    viewport: u16,
+    /// Cut long lines off instead of wrapping them.
+    nowrap: bool,
 }
 
 impl ContentPane {
     pub fn new() -> Self {
         Self::default()
     }
+
+    fn send_action(&self, action: Action) -> Result<()> {
+        if let Some(tx) = &self.command_tx {
+            tx.send(action.clone())?;
+        }
+        Ok(())
+    }
+
+    /// The active note rendered for a pane `width` columns wide, reusing the last render.
+    fn rendered(&mut self, content: &str, width: u16) -> Text<'static> {
+        match &self.rendered {
+            Some((rendered, w, text)) if rendered == content && *w == width => text.clone(),
+            _ => {
+                let text = markdown::render(content, &self.markdown, width);
+                self.rendered = Some((content.to_string(), width, text.clone()));
+                text
+            }
+        }
+    }
+
+    fn mode(&self) -> Mode {
+        Mode::Content
+    }
+
+    fn is_focused(&self, state: &State) -> bool {
+        state.mode == self.mode() && self.editor.is_none()
+    }
+
+    fn scroll_by(&mut self, lines: i32) {
+        let scroll = (self.scroll as i32 + lines).clamp(0, self.max_scroll as i32);
+        self.scroll = scroll as u16;
+    }
+
+    fn close_editor(&mut self, state: &mut State) {
+        if let Some(editor) = self.editor.take() {
+            state.mode = editor.return_mode;
+            state.input_mode = InputMode::Normal;
+        }
+    }
 }
 
 impl Component for ContentPane {
@@ -29,20 +96,105 @@ fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()>
     }
 
     fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.markdown = MarkdownStyles::from_config(&config);
         self.config = config;
         Ok(())
     }
 
+    fn register_store_handler(&mut self, store: SharedStore) -> Result<()> {
+        self.store = Some(store);
+        Ok(())
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent, state: &mut State) -> Result<Option<Action>> {
+        let Some(editor) = &mut self.editor else {
+            return Ok(None);
+        };
+        match editor.handle_key(key) {
+            EditorEvent::Continue => Ok(None),
+            EditorEvent::Save(Some(mutation)) => {
+                // Closed once the note is saved, or left open to retry if it fails
+                editor.start_saving();
+                Ok(Some(mutation))
+            }
+            EditorEvent::Save(None) => {
+                self.close_editor(state);
+                Ok(None)
+            }
+            EditorEvent::Cancel => {
+                self.close_editor(state);
+                Ok(None)
+            }
+        }
+    }
+
+    fn handle_mouse_events(
+        &mut self,
+        mouse: MouseEvent,
+        _state: &mut State,
+    ) -> Result<Option<Action>> {
+        match mouse.kind {
+            MouseEventKind::ScrollDown => Ok(Some(Action::ScrollDown)),
+            MouseEventKind::ScrollUp => Ok(Some(Action::ScrollUp)),
+            _ => Ok(None),
+        }
+    }
+
     fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
         match action {
+            Action::Tick if self.editor.is_some() => {
+                self.send_action(Action::StatusLine(editor::STATUS_LINE.to_string()))?;
+            }
+            Action::NotesChanged { .. } if self.editor.as_ref().is_some_and(|e| e.is_saving()) => {
+                self.close_editor(state);
+            }
+            Action::Error(ref error) => {
+                if let Some(editor) = self.editor.as_mut().filter(|e| e.is_saving()) {
+                    editor.save_failed(error);
+                }
+            }
+            Action::OpenEditor(draft) if !self.config.config.external_editor => {
+                self.editor = Some(NoteEditor::new(draft, state.mode));
+                state.mode = Mode::Content;
+                state.input_mode = InputMode::Insert;
+            }
+            Action::Tick if self.is_focused(state) => {
+                let status_line = build_status_line(&self.config, &self.mode());
+                self.send_action(Action::StatusLine(status_line))?;
+            }
+            Action::ToggleMarkdown => self.raw = !self.raw,
+            Action::ToggleWrap => self.nowrap = !self.nowrap,
+            Action::ScrollDown => self.scroll_by(1),
+            Action::ScrollUp => self.scroll_by(-1),
+            Action::ScrollHalfPageDown => self.scroll_by((self.viewport / 2).max(1) as i32),
+            Action::ScrollHalfPageUp => self.scroll_by(-((self.viewport / 2).max(1) as i32)),
+            Action::ScrollToTop => self.scroll = 0,
+            Action::ScrollToBottom => self.scroll = self.max_scroll,
             Action::Tick => {}
             Action::Render => {}
             Action::FocusNext => {}
-            Action::FocusPrev => {}
+            // Change to page pane
+            Action::FocusPrev if self.is_focused(state) => {
+                self.send_action(Action::Focus(Mode::Page))?;
+            }
             Action::LoadActivePageContent => {
                 if let Some(page) = state.get_active_page() {
-                    let page_info = self.dnote.get_page_content(page.id)?;
-                    state.page_content = Some(page_info.content);
+                    state.loading.content = true;
+                    spawn_store_task(&self.store, &self.command_tx, move |store| {
+                        let page_info = store.get_page_content(page.id)?;
+                        Ok(Action::ActivePageContentLoaded(page.id, page_info.content))
+                    })?;
+                }
+            }
+            Action::ActivePageContentLoaded(page_id, content) => {
+                if state.is_active_page(page_id) {
+                    state.loading.content = false;
+                    state.page_content = Some(content);
+                    if self.note.replace(page_id) != Some(page_id) {
+                        self.scroll = 0;
+                    }
+                } else {
+                    log::debug!("Discarding stale content for page {page_id}");
                 }
             }
             _ => {}
@@ -51,20 +203,73 @@ fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>
     }
 
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<()> {
+        if let Some(editor) = &mut self.editor {
+            editor.draw(f, area);
+            return Ok(());
+        }
         let title = Title::from(" Content ".cyan().bold());
+        let title_loading = loading_title(state.loading.content);
         let title_padding = Line::from("");
+        let flags: Vec<&str> = [(self.raw, "raw"), (self.nowrap, "nowrap")]
+            .into_iter()
+            .filter_map(|(set, flag)| set.then_some(flag))
+            .collect();
+        let title_flags = match flags.is_empty() {
+            true => Line::default(),
+            false => Line::from(format!(" {} ", flags.join(" ")).dark_gray()),
+        };
+        let border_style = match self.is_focused(state) {
+            true => Style::default().cyan(),
+            false => Style::default(),
+        };
         // let block = Block::default().borders(Borders::ALL).title(title);
         let block = Block::default()
             .borders(Borders::ALL)
             // .padding(Padding::proportional(1))
             .border_set(border::ROUNDED)
+            .border_style(border_style)
             .title(title_padding.clone().left_aligned())
-            .title(title);
-        let mut paragraph = Paragraph::new("".to_string()).block(block.clone());
-        if let Some(note) = &state.page_content {
-            paragraph = Paragraph::new(note.to_string()).block(block.clone());
+            .title(title)
+            .title(title_flags)
+            .title(title_loading);
+        let inner = block.inner(area);
+        let text = match &state.page_content {
+            Some(note) if self.raw => Text::from(note.to_string()),
+            Some(note) => self.rendered(note, inner.width),
+            None => Text::default(),
+        };
+        let mut paragraph = Paragraph::new(text);
+        if !self.nowrap {
+            paragraph = paragraph.wrap(Wrap { trim: false });
         }
+
+        let lines = paragraph.line_count(inner.width) as u16;
+        self.viewport = inner.height;
+        self.max_scroll = lines.saturating_sub(inner.height);
+        self.scroll = self.scroll.min(self.max_scroll);
+        let block = match self.max_scroll {
+            0 => block,
+            _ => {
+                let last = (self.scroll + inner.height).min(lines);
+                let position = format!(" {}-{last} of {lines} ", self.scroll + 1);
+                block.title_bottom(Line::from(position).right_aligned())
+            }
+        };
+        let paragraph = paragraph.scroll((self.scroll, 0)).block(block);
         f.render_widget(paragraph, area);
+
+        if self.max_scroll > 0 {
+            let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None);
+            let mut scrollbar_state =
+                ScrollbarState::new(self.max_scroll as usize).position(self.scroll as usize);
+            let track = area.inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            });
+            f.render_stateful_widget(scrollbar, track, &mut scrollbar_state);
+        }
         Ok(())
     }
 }
@@ -0,0 +1,243 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::*,
+    symbols::border,
+    widgets::{block::Title, Block, Borders},
+};
+use tui_textarea::TextArea;
+
+use super::Frame;
+use crate::{
+    action::{Action, NoteDraft},
+    state::Mode,
+    store,
+};
+
+/// Keys of the built-in editor, shown in the footer while it is open.
+pub const STATUS_LINE: &str =
+    "[Save → Ctrl-s] [Cancel → Esc] [Undo → Ctrl-z, Ctrl-u] [Redo → Ctrl-r] [Select → Shift-arrows] [Copy/Cut/Paste → Ctrl-c/x/y]";
+
+/// What a key press did to the editor.
+#[derive(Debug, PartialEq, Eq)]
+pub enum EditorEvent {
+    /// The editor stays open.
+    Continue,
+    /// The note was saved with this mutation, if it changed.
+    Save(Option<Action>),
+    /// The changes were discarded.
+    Cancel,
+}
+
+/// Multi-line editor for a [`NoteDraft`], shown in the content pane in [`Mode::Content`].
+pub struct NoteEditor {
+    draft: NoteDraft,
+    textarea: TextArea<'static>,
+    /// Mode to return to once the editor is closed.
+    pub return_mode: Mode,
+    error: Option<String>,
+    /// Set by Esc on a modified note, a second Esc discards the changes.
+    confirm_discard: bool,
+    /// Set while the note is being saved, the editor stays open until the save succeeds.
+    saving: bool,
+}
+
+impl NoteEditor {
+    pub fn new(draft: NoteDraft, return_mode: Mode) -> Self {
+        let mut textarea = TextArea::new(draft.content.lines().map(String::from).collect());
+        textarea.set_cursor_line_style(Style::default());
+        textarea.set_selection_style(Style::default().on_blue());
+        textarea.set_placeholder_text("Write your note…");
+        Self {
+            draft,
+            textarea,
+            return_mode,
+            error: None,
+            confirm_discard: false,
+            saving: false,
+        }
+    }
+
+    pub fn is_saving(&self) -> bool {
+        self.saving
+    }
+
+    /// Wait for the save just requested, ignoring keys until it succeeds or fails.
+    pub fn start_saving(&mut self) {
+        self.saving = true;
+    }
+
+    /// Keep editing after the save failed with `error`, so nothing written is lost.
+    pub fn save_failed(&mut self, error: &str) {
+        self.saving = false;
+        self.error = Some(format!("Failed to save: {error}"));
+    }
+
+    pub fn content(&self) -> String {
+        self.textarea.lines().join("\n")
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.content().trim_end() != self.draft.content.trim_end()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> EditorEvent {
+        if self.saving {
+            return EditorEvent::Continue;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let confirm_discard = std::mem::take(&mut self.confirm_discard);
+        match key.code {
+            KeyCode::Char('s') if ctrl => {
+                let content = self.content().trim_end().to_string();
+                if let Err(err) = store::validate_content(&content) {
+                    self.error = Some(err.to_string());
+                    return EditorEvent::Continue;
+                }
+                return EditorEvent::Save(self.draft.clone().save(content));
+            }
+            KeyCode::Esc if self.is_modified() && !confirm_discard => {
+                self.confirm_discard = true;
+                self.error = Some("Unsaved changes, press Esc again to discard them".into());
+                return EditorEvent::Continue;
+            }
+            KeyCode::Esc => return EditorEvent::Cancel,
+            KeyCode::Char('z') if ctrl => {
+                self.textarea.undo();
+            }
+            _ => {
+                self.textarea.input(key);
+            }
+        }
+        self.error = None;
+        EditorEvent::Continue
+    }
+
+    pub fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let title = match self.draft.id {
+            Some(id) => format!(" Editing note {id} "),
+            None => format!(" New note in {} ", self.draft.book),
+        };
+        let modified = match self.is_modified() {
+            true => Line::from(" [+] ".yellow().bold()),
+            false => Line::default(),
+        };
+        let title_bottom = match &self.error {
+            _ if self.saving => Line::from(" Saving… ".dark_gray().italic()),
+            Some(error) => Line::from(format!(" {error} ").red().bold()),
+            None => Line::from(" Ctrl-s to save, Esc to cancel ".dark_gray()),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(border::ROUNDED)
+            .style(Style::default().cyan())
+            .title(Line::from("").left_aligned())
+            .title(Title::from(title.cyan().bold()))
+            .title(modified)
+            .title_bottom(title_bottom.right_aligned());
+        self.textarea.set_block(block);
+        self.textarea.set_style(Style::default().white());
+        f.render_widget(&self.textarea, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn type_str(editor: &mut NoteEditor, s: &str) {
+        for c in s.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            assert_eq!(editor.handle_key(key(code)), EditorEvent::Continue);
+        }
+    }
+
+    #[test]
+    fn editor_saves_new_notes() {
+        let mut editor = NoteEditor::new(NoteDraft::new_note("rust".into()), Mode::Page);
+        assert_eq!(editor.handle_key(ctrl('s')), EditorEvent::Continue);
+        assert_eq!(
+            editor.error.as_deref(),
+            Some("Note content cannot be empty")
+        );
+        type_str(&mut editor, "# Title\nbody\n\n");
+        assert_eq!(
+            editor.handle_key(ctrl('s')),
+            EditorEvent::Save(Some(Action::AddNote {
+                book: "rust".into(),
+                content: "# Title\nbody".into(),
+            }))
+        );
+    }
+
+    #[test]
+    fn editor_saves_edits_and_skips_unchanged_notes() {
+        let draft = NoteDraft::edit_note("rust".into(), 3, "cargo".into());
+        let mut editor = NoteEditor::new(draft.clone(), Mode::Page);
+        assert_eq!(editor.handle_key(ctrl('s')), EditorEvent::Save(None));
+        editor.handle_key(ctrl('e'));
+        type_str(&mut editor, " clippy");
+        assert_eq!(
+            editor.handle_key(ctrl('s')),
+            EditorEvent::Save(Some(Action::EditNote {
+                id: 3,
+                content: "cargo clippy".into(),
+            }))
+        );
+    }
+
+    #[test]
+    fn editor_keeps_the_note_while_saving_and_after_a_failed_save() {
+        let mut editor = NoteEditor::new(NoteDraft::new_note("rust".into()), Mode::Page);
+        type_str(&mut editor, "cargo");
+        editor.start_saving();
+        assert_eq!(editor.handle_key(key(KeyCode::Esc)), EditorEvent::Continue);
+        type_str(&mut editor, "x");
+        assert_eq!(editor.content(), "cargo");
+        editor.save_failed("book is locked");
+        assert!(!editor.is_saving());
+        assert_eq!(
+            editor.error.as_deref(),
+            Some("Failed to save: book is locked")
+        );
+        type_str(&mut editor, " fmt");
+        assert_eq!(editor.content(), "cargo fmt");
+    }
+
+    #[test]
+    fn editor_undoes_and_redoes() {
+        let mut editor = NoteEditor::new(NoteDraft::new_note("rust".into()), Mode::Page);
+        type_str(&mut editor, "ab");
+        editor.handle_key(ctrl('z'));
+        assert_eq!(editor.content(), "a");
+        editor.handle_key(ctrl('r'));
+        assert_eq!(editor.content(), "ab");
+        editor.handle_key(ctrl('u'));
+        assert_eq!(editor.content(), "a");
+    }
+
+    #[test]
+    fn editor_asks_before_discarding_changes() {
+        let draft = NoteDraft::edit_note("rust".into(), 3, "cargo".into());
+        let mut editor = NoteEditor::new(draft.clone(), Mode::Page);
+        assert_eq!(editor.handle_key(key(KeyCode::Esc)), EditorEvent::Cancel);
+
+        let mut editor = NoteEditor::new(draft, Mode::Page);
+        type_str(&mut editor, "x");
+        assert_eq!(editor.handle_key(key(KeyCode::Esc)), EditorEvent::Continue);
+        assert!(editor.error.is_some());
+        assert_eq!(editor.handle_key(key(KeyCode::Esc)), EditorEvent::Cancel);
+    }
+}
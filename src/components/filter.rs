@@ -0,0 +1,116 @@
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use tui_input::{backend::crossterm::EventHandler, Input};
+
+use super::Frame;
+
+/// Keys of the filter line, shown in the footer while it is open.
+pub const STATUS_LINE: &str = "[Filter → type] [Keep → Enter] [Clear → Esc]";
+
+/// What a key press did to the filter line.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FilterEvent {
+    /// The line stays open with the same query.
+    Continue,
+    /// The query changed to this.
+    Changed(String),
+    /// The line was closed, keeping the filter.
+    Keep,
+    /// The line was closed, dropping the filter.
+    Clear,
+}
+
+/// Inline line filtering the books or pages list as you type, opened with `/`.
+#[derive(Default)]
+pub struct FilterLine {
+    input: Input,
+    open: bool,
+}
+
+impl FilterLine {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Open the line to edit `query`, the list's current filter if any.
+    pub fn open(&mut self, query: &str) {
+        self.input = Input::new(query.to_string());
+        self.open = true;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FilterEvent {
+        match key.code {
+            KeyCode::Enter => {
+                self.open = false;
+                FilterEvent::Keep
+            }
+            KeyCode::Esc => {
+                self.open = false;
+                FilterEvent::Clear
+            }
+            _ => match self.input.handle_event(&Event::Key(key)) {
+                Some(change) if change.value => FilterEvent::Changed(self.input.value().into()),
+                _ => FilterEvent::Continue,
+            },
+        }
+    }
+
+    /// Draw the line at the bottom of `area`, the inside of a list pane, while it is open or
+    /// the list is filtered by `query`. Returns the area left for the list.
+    pub fn draw(&self, f: &mut Frame<'_>, area: Rect, query: Option<&str>) -> Rect {
+        let query = match (self.open, query) {
+            (true, _) => self.input.value(),
+            (false, Some(query)) => query,
+            (false, None) => return area,
+        };
+        let [list_area, line_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        let style = match self.open {
+            true => Style::default().yellow(),
+            false => Style::default().dark_gray(),
+        };
+        // Keep the cursor in view, after the leading slash
+        let width = line_area.width.max(2) - 2;
+        let scroll = self.input.visual_scroll(width as usize);
+        let line = Line::from(vec![
+            Span::styled("/", style),
+            Span::styled(query.chars().skip(scroll).collect::<String>(), style),
+        ]);
+        f.render_widget(line, line_area);
+        if self.open {
+            f.set_cursor_position(Position {
+                x: line_area.x + 1 + (self.input.visual_cursor().max(scroll) - scroll) as u16,
+                y: line_area.y,
+            });
+        }
+        list_area
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn filter_line_reports_query_changes() {
+        let mut line = FilterLine::default();
+        line.open("ru");
+        assert!(line.is_open());
+        assert_eq!(
+            line.handle_key(key(KeyCode::Char('s'))),
+            FilterEvent::Changed("rus".into())
+        );
+        assert_eq!(line.handle_key(key(KeyCode::Left)), FilterEvent::Continue);
+        assert_eq!(line.handle_key(key(KeyCode::Enter)), FilterEvent::Keep);
+        assert!(!line.is_open());
+        line.open("");
+        assert_eq!(line.handle_key(key(KeyCode::Esc)), FilterEvent::Clear);
+    }
+}
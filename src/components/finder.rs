@@ -0,0 +1,306 @@
+use color_eyre::eyre::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::*,
+    symbols::border,
+    widgets::{Block, Borders, Clear, List, ListItem, Padding, Paragraph, Wrap},
+};
+use tokio::sync::mpsc::UnboundedSender;
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+
+use super::{loading_title, spawn_store_task, Component, Frame};
+use crate::{
+    action::Action,
+    config::Config,
+    dnote::NoteId,
+    finder::{rank, FinderItem, FinderMatch},
+    state::{State, StatefulList},
+    store::SharedStore,
+};
+
+const STATUS_LINE: &str = "Type to filter books and notes  ↑/↓ Select  ⏎ Open  Esc Cancel";
+
+/// Telescope-style picker over every book and note, with a preview of the selected one.
+#[derive(Default)]
+pub struct FinderPopup {
+    input: Input,
+    items: Vec<FinderItem>,
+    matches: StatefulList<FinderMatch>,
+    loading: bool,
+    /// Content of the selected note, once loaded.
+    preview: Option<(NoteId, String)>,
+    store: Option<SharedStore>,
+    command_tx: Option<UnboundedSender<Action>>,
+    config: Config,
+}
+
+impl FinderPopup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn send_action(&self, action: Action) -> Result<()> {
+        if let Some(tx) = &self.command_tx {
+            tx.send(action.clone())?;
+        }
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&FinderItem> {
+        let selected = self.matches.state.selected()?;
+        let index = self.matches.items.get(selected)?.index;
+        self.items.get(index)
+    }
+
+    /// Rank the items against the query, selecting the best match.
+    fn filter(&mut self) -> Result<()> {
+        self.matches = StatefulList::with_items(rank(&self.items, self.input.value()));
+        self.matches.next();
+        self.load_preview()
+    }
+
+    fn select_next(&mut self) -> Result<()> {
+        self.matches.next();
+        self.load_preview()
+    }
+
+    fn select_prev(&mut self) -> Result<()> {
+        self.matches.previous();
+        self.load_preview()
+    }
+
+    /// Load the content of the selected note, unless it is already shown.
+    fn load_preview(&mut self) -> Result<()> {
+        let Some(&FinderItem::Note { id, .. }) = self.selected() else {
+            return Ok(());
+        };
+        if self.preview.as_ref().is_some_and(|(shown, _)| *shown == id) {
+            return Ok(());
+        }
+        spawn_store_task(&self.store, &self.command_tx, move |store| {
+            let content = store.get_page_content(id)?.content;
+            Ok(Action::FinderPreviewLoaded(id, content))
+        })
+    }
+
+    fn match_item(&self, m: &FinderMatch) -> ListItem<'static> {
+        let item = &self.items[m.index];
+        let book_len = item.book().chars().count();
+        let base = |i: usize| match item {
+            FinderItem::Book(_) => Style::default().blue().bold(),
+            FinderItem::Note { .. } if i < book_len => Style::default().blue(),
+            FinderItem::Note { .. } => Style::default().white(),
+        };
+        let mut spans: Vec<Span> = Vec::new();
+        let mut matched = m.indices.iter().peekable();
+        for (i, c) in item.text().chars().enumerate() {
+            let style = match matched.next_if_eq(&&(i as u32)) {
+                Some(_) => base(i).yellow().bold(),
+                None => base(i),
+            };
+            match spans.last_mut() {
+                Some(span) if span.style == style => span.content.to_mut().push(c),
+                _ => spans.push(Span::styled(c.to_string(), style)),
+            }
+        }
+        let suffix = match item {
+            FinderItem::Book(_) => " (book)".to_string(),
+            FinderItem::Note { id, .. } => format!(" ({id})"),
+        };
+        spans.push(Span::styled(suffix, Style::default().dark_gray()));
+        ListItem::new(Line::from(spans))
+    }
+
+    fn preview_text(&self) -> Text<'static> {
+        match self.selected() {
+            Some(FinderItem::Book(book)) => {
+                let notes: Vec<Line> = self
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        FinderItem::Note {
+                            book: b,
+                            id,
+                            summary,
+                        } if b == book => Some(Line::from(vec![
+                            Span::styled(format!("[{id}]"), Style::default().green()),
+                            Span::raw(format!(" {summary}")),
+                        ])),
+                        _ => None,
+                    })
+                    .collect();
+                Text::from(notes)
+            }
+            Some(FinderItem::Note { id, .. }) => match &self.preview {
+                Some((shown, content)) if shown == id => Text::from(content.clone()),
+                _ => Text::from("loading…".dark_gray().italic()),
+            },
+            None => Text::default(),
+        }
+    }
+}
+
+impl Component for FinderPopup {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn register_store_handler(&mut self, store: SharedStore) -> Result<()> {
+        self.store = Some(store);
+        Ok(())
+    }
+
+    fn init(&mut self, _area: Size) -> Result<()> {
+        self.loading = true;
+        spawn_store_task(&self.store, &self.command_tx, |store| {
+            let mut items = Vec::new();
+            for book in store.get_books()? {
+                let pages = store.get_pages(&book.name)?;
+                items.push(FinderItem::Book(book.name.clone()));
+                items.extend(pages.into_iter().map(|page| FinderItem::Note {
+                    book: book.name.clone(),
+                    id: page.id,
+                    summary: page.summary,
+                }));
+            }
+            Ok(Action::FinderItemsLoaded(items))
+        })
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Ok(Some(Action::ClosePopup)),
+            KeyCode::Enter => {
+                let jump = match self.selected() {
+                    Some(FinderItem::Book(book)) => Action::JumpToBook(book.clone()),
+                    Some(FinderItem::Note { book, id, .. }) => Action::JumpToNote {
+                        book: book.clone(),
+                        id: *id,
+                    },
+                    None => return Ok(None),
+                };
+                self.send_action(Action::ClosePopup)?;
+                self.send_action(jump)?;
+            }
+            KeyCode::Down => self.select_next()?,
+            KeyCode::Char('n') if ctrl => self.select_next()?,
+            KeyCode::Up => self.select_prev()?,
+            KeyCode::Char('p') if ctrl => self.select_prev()?,
+            _ => {
+                let changed = self
+                    .input
+                    .handle_event(&Event::Key(key))
+                    .is_some_and(|change| change.value);
+                if changed {
+                    self.filter()?;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action, _state: &mut State) -> Result<Option<Action>> {
+        match action {
+            Action::Tick => self.send_action(Action::StatusLine(STATUS_LINE.to_string()))?,
+            Action::FinderItemsLoaded(items) => {
+                self.loading = false;
+                self.items = items;
+                self.filter()?;
+            }
+            Action::FinderPreviewLoaded(id, content) => {
+                if matches!(self.selected(), Some(FinderItem::Note { id: selected, .. }) if *selected == id)
+                {
+                    self.preview = Some((id, content));
+                }
+            }
+            Action::Error(_) => self.loading = false,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect, _state: &mut State) -> Result<()> {
+        f.render_widget(Clear, area);
+
+        let outer_block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(border::ROUNDED)
+            .title_top("> Find <")
+            .title_alignment(Alignment::Center)
+            .padding(Padding::horizontal(1))
+            .style(Style::default().blue());
+        f.render_widget(outer_block.clone(), area);
+        let inner_area = outer_block.inner(area);
+
+        let columns = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .spacing(1)
+            .split(inner_area);
+        let rows = Layout::vertical([
+            Constraint::Length(3), // Input field
+            Constraint::Fill(1),   // Matches
+            Constraint::Length(1), // Bottom text
+        ])
+        .split(columns[0]);
+
+        let width = rows[0].width.max(3) - 3; // keep 2 for borders and 1 for cursor
+        let scroll = self.input.visual_scroll(width as usize);
+        let input = Paragraph::new(self.input.value())
+            .style(Style::default().yellow())
+            .scroll((0, scroll as u16))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(border::ROUNDED)
+                    .title("Book or note")
+                    .title(loading_title(self.loading)),
+            );
+        f.render_widget(input, rows[0]);
+
+        let items: Vec<ListItem> = self
+            .matches
+            .items
+            .iter()
+            .map(|m| self.match_item(m))
+            .collect();
+        let title_count =
+            Line::from(format!(" {} / {} ", items.len(), self.items.len())).right_aligned();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::TOP)
+                    .title(title_count.blue().bold()),
+            )
+            .highlight_style(Style::default().on_black().bold());
+        f.render_stateful_widget(list, rows[1], &mut self.matches.state);
+
+        let bottom_text = Paragraph::new("Press Esc to cancel, Enter to open")
+            .style(Style::default().dark_gray())
+            .alignment(Alignment::Center);
+        f.render_widget(bottom_text, rows[2]);
+
+        let preview = Paragraph::new(self.preview_text())
+            .style(Style::default().white())
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(border::ROUNDED)
+                    .title("Preview"),
+            );
+        f.render_widget(preview, columns[1]);
+
+        f.set_cursor_position(Position {
+            x: rows[0].x + ((self.input.visual_cursor()).max(scroll) - scroll) as u16 + 1,
+            y: rows[0].y + 1,
+        });
+        Ok(())
+    }
+}
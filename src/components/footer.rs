@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use color_eyre::eyre::Result;
 use ratatui::prelude::*;
 use tokio::sync::mpsc::UnboundedSender;
@@ -7,19 +9,45 @@
     components::Component,
     config::Config,
     state::{InputMode, State},
+    store::count_notes,
 };
 
+/// How long a toast stays visible in the footer.
+const TOAST_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Frames of the spinner shown while a sync is running, advanced on every tick.
+const SPINNER: [&str; 4] = ["|", "/", "-", "\\"];
+
+/// A short-lived message shown in place of the status line.
+struct Toast {
+    message: String,
+    style: Style,
+    since: Instant,
+}
+
 #[derive(Default)]
 pub struct FooterPane {
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
     status_line: String,
+    toast: Option<Toast>,
+    ticks: usize,
 }
 
 impl FooterPane {
     pub fn new() -> Self {
         Self::default()
     }
+
+    fn show_toast(&mut self, message: &str, style: Style) {
+        // Keep the toast on a single line, stderr output often spans several
+        let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.toast = Some(Toast {
+            message,
+            style,
+            since: Instant::now(),
+        });
+    }
 }
 
 impl Component for FooterPane {
@@ -35,16 +63,50 @@ fn register_config_handler(&mut self, config: Config) -> Result<()> {
 
     fn update(&mut self, action: Action, _state: &mut State) -> Result<Option<Action>> {
         match action {
-            Action::Tick => {}
+            Action::Tick => self.ticks = self.ticks.wrapping_add(1),
             Action::StatusLine(ref s) => self.status_line.clone_from(s),
+            Action::Error(ref e) => {
+                self.show_toast(&format!("✗ {e}"), Style::default().red().bold())
+            }
+            Action::Sync => self.toast = None,
+            Action::NotesChanged { ref message, .. } => {
+                self.show_toast(&format!("✓ {message}"), Style::default().green().bold())
+            }
+            Action::NotesExported { count, ref path } => self.show_toast(
+                &format!("✓ Exported {} to {}", count_notes(count), path.display()),
+                Style::default().green().bold(),
+            ),
+            Action::SyncCompleted => self.show_toast("✓ Synced", Style::default().green().bold()),
+            Action::SyncFailed(_) => {
+                self.show_toast("✗ Sync failed", Style::default().red().bold())
+            }
             _ => {}
         }
         Ok(None)
     }
 
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<()> {
-        let status = format!("  {}  ", self.status_line);
-        let footer_line = Line::from(vec![Span::styled(status, Style::default())]).dark_gray();
+        if self
+            .toast
+            .as_ref()
+            .is_some_and(|toast| toast.since.elapsed() >= TOAST_TIMEOUT)
+        {
+            self.toast = None;
+        }
+        let footer_line = match &self.toast {
+            _ if state.loading.sync => Line::from(vec![Span::styled(
+                format!("  {} Syncing…  ", SPINNER[self.ticks % SPINNER.len()]),
+                Style::default().yellow(),
+            )]),
+            Some(toast) => Line::from(vec![Span::styled(
+                format!("  {}  ", toast.message),
+                toast.style,
+            )]),
+            None => {
+                let status = format!("  {}  ", self.status_line);
+                Line::from(vec![Span::styled(status, Style::default())]).dark_gray()
+            }
+        };
         f.render_widget(footer_line, area);
         f.render_widget(
             Line::from(vec![match state.input_mode {
@@ -1,4 +1,6 @@
+use chrono::Utc;
 use color_eyre::eyre::Result;
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{
     prelude::*,
     symbols::border,
@@ -6,19 +8,33 @@
 };
 use tokio::sync::mpsc::UnboundedSender;
 
-use super::{Component, Frame};
+use super::{
+    filter::{self, FilterEvent, FilterLine},
+    filtered_title, format_relative_time, list_item_at, loading_title, spawn_store_task, Component,
+    Frame,
+};
 use crate::{
-    action::Action,
+    action::{Action, NoteDraft},
     config::{build_status_line, Config},
-    dnote::Dnote,
-    state::{Mode, State, StatefulList},
+    dnote::DnotePage,
+    sort::{PageSort, PageSorts},
+    state::{InputMode, Mode, State},
+    store::{count_notes, SharedStore},
 };
 
+/// Summaries of the notes to delete listed in the confirm popup, it only fits a few.
+const CONFIRM_SUMMARIES: usize = 3;
+
 #[derive(Default)]
 pub struct PagesPane {
-    dnote: Dnote,
+    store: Option<SharedStore>,
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
+    /// Sort chosen for each book.
+    sorts: PageSorts,
+    filter: FilterLine,
+    /// Where the list was last drawn, to find the page under the mouse.
+    list_area: Rect,
 }
 
 impl PagesPane {
@@ -44,6 +60,28 @@ fn send_action(&self, action: Action) -> Result<()> {
         }
         Ok(())
     }
+
+    /// Load the content of the active page if `before` was another one, e.g. after filtering.
+    fn active_page_changed(&self, state: &mut State, before: Option<DnotePage>) -> Result<()> {
+        match state.get_active_page() {
+            page if page == before => {}
+            Some(_) => self.send_action(Action::LoadActivePageContent)?,
+            None => state.page_content = None,
+        }
+        Ok(())
+    }
+
+    /// Remember `sort` for the active book, and reload its pages in that order.
+    fn set_sort(&mut self, state: &State, sort: impl FnOnce(PageSort) -> PageSort) -> Result<()> {
+        if let Some(book) = state.get_active_book() {
+            let sort = sort(self.sorts.get(&book.name));
+            if let Err(err) = self.sorts.set(&book.name, sort) {
+                log::error!("Failed to save the sort of book {}: {err}", book.name);
+            }
+            self.send_action(Action::LoadActiveBookPages)?;
+        }
+        Ok(())
+    }
 }
 
 impl Component for PagesPane {
@@ -53,36 +91,143 @@ fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()>
     }
 
     fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.sorts = PageSorts::load(config.config.data_dir.join("page_sorts.json"));
         self.config = config;
         Ok(())
     }
 
+    fn register_store_handler(&mut self, store: SharedStore) -> Result<()> {
+        self.store = Some(store);
+        Ok(())
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent, state: &mut State) -> Result<Option<Action>> {
+        if !self.filter.is_open() {
+            return Ok(None);
+        }
+        let before = state.get_active_page();
+        match self.filter.handle_key(key) {
+            FilterEvent::Continue => {}
+            FilterEvent::Changed(query) => state.pages.filter(&query, page_matches),
+            FilterEvent::Keep if state.pages.filter_query() != Some("") => {
+                state.input_mode = InputMode::Normal;
+            }
+            FilterEvent::Keep | FilterEvent::Clear => {
+                state.input_mode = InputMode::Normal;
+                state.pages.clear_filter();
+            }
+        }
+        self.active_page_changed(state, before)?;
+        Ok(None)
+    }
+
+    fn handle_mouse_events(
+        &mut self,
+        mouse: MouseEvent,
+        state: &mut State,
+    ) -> Result<Option<Action>> {
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let position = Position::new(mouse.column, mouse.row);
+                if let Some(index) = list_item_at(&state.pages, self.list_area, position) {
+                    let before = state.get_active_page();
+                    state.pages.state.select(Some(index));
+                    self.active_page_changed(state, before)?;
+                }
+                Ok(None)
+            }
+            MouseEventKind::ScrollDown => Ok(Some(Action::SelectNextPage)),
+            MouseEventKind::ScrollUp => Ok(Some(Action::SelectPrevPage)),
+            _ => Ok(None),
+        }
+    }
+
     fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>> {
         match action {
-            Action::Tick => {
-                if self.is_focused(state) {
-                    let status_line = self.get_status_line();
-                    self.send_action(Action::StatusLine(status_line))?;
-                }
+            Action::Tick if self.filter.is_open() => {
+                self.send_action(Action::StatusLine(filter::STATUS_LINE.to_string()))?;
+            }
+            Action::Tick if self.is_focused(state) => {
+                let status_line = self.get_status_line();
+                self.send_action(Action::StatusLine(status_line))?;
             }
-            Action::FocusNext => {}
-            Action::FocusPrev => {
+            // Change to content pane
+            Action::FocusNext if self.is_focused(state) && state.get_active_page().is_some() => {
+                self.send_action(Action::Focus(Mode::Content))?;
+            }
+            Action::FocusPrev if self.is_focused(state) => {
                 // Change to book pane
-                state.mode = Mode::Book;
+                self.send_action(Action::Focus(Mode::Book))?;
                 state.page_content = None;
             }
             Action::LoadActiveBookPages => {
                 if let Some(book) = state.get_active_book() {
-                    let pages = self.dnote.get_pages(&book.name)?;
-                    state.pages = StatefulList::with_items(pages);
+                    state.loading.pages = true;
+                    let sort = self.sorts.get(&book.name);
+                    spawn_store_task(&self.store, &self.command_tx, move |store| {
+                        let mut pages = store.get_pages(&book.name)?;
+                        sort.sort(store, &mut pages)?;
+                        Ok(Action::ActiveBookPagesLoaded(book.name, pages))
+                    })?;
                 }
             }
             Action::UpdateActiveBookPages => {
                 if let Some(book) = state.get_active_book() {
-                    let new_pages = self.dnote.get_pages(&book.name)?;
-                    state.update_pages(new_pages);
+                    state.loading.pages = true;
+                    let sort = self.sorts.get(&book.name);
+                    spawn_store_task(&self.store, &self.command_tx, move |store| {
+                        let mut pages = store.get_pages(&book.name)?;
+                        sort.sort(store, &mut pages)?;
+                        Ok(Action::ActiveBookPagesUpdated(book.name, pages))
+                    })?;
                 }
             }
+            Action::ActiveBookPagesLoaded(book_name, pages) => {
+                if state.is_active_book(&book_name) {
+                    state.loading.pages = false;
+                    let kept = state.pages.replace_items_by(pages, |page| page.id);
+                    // A search hit asked for a note of this book
+                    let jumped = state.pending_page.take().is_some_and(|id| {
+                        // Jumping shows the note even when the filter hides it
+                        if !state.pages.items.iter().any(|p| p.id == id) {
+                            state.pages.clear_filter();
+                        }
+                        state.select_page_by_id(id)
+                    });
+                    if kept || jumped {
+                        self.send_action(Action::LoadActivePageContent)?;
+                    } else if self.is_focused(state) {
+                        // Pages may arrive after the pane was focused, select the first one then
+                        self.send_action(Action::SelectNextPage)?;
+                    }
+                } else {
+                    log::debug!("Discarding stale pages for book {book_name}");
+                }
+            }
+            Action::ActiveBookPagesUpdated(book_name, pages) => {
+                if state.is_active_book(&book_name) {
+                    state.loading.pages = false;
+                    state.update_pages(pages);
+                } else {
+                    log::debug!("Discarding stale pages for book {book_name}");
+                }
+            }
+            Action::OpenFilter if self.is_focused(state) => {
+                let query = state.pages.filter_query().unwrap_or_default().to_string();
+                state.pages.filter(&query, page_matches);
+                self.filter.open(&query);
+                state.input_mode = InputMode::Insert;
+            }
+            Action::NextMatch | Action::PrevMatch
+                if self.is_focused(state) && state.pages.filter.is_some() =>
+            {
+                let before = state.get_active_page();
+                match action {
+                    Action::NextMatch => state.pages.next_wrapping(),
+                    _ => state.pages.previous_wrapping(),
+                }
+                self.active_page_changed(state, before)?;
+            }
             Action::SelectNextPage => {
                 state.select_next_page();
                 self.send_action(Action::LoadActivePageContent)?;
@@ -91,24 +236,78 @@ fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>
                 state.select_prev_page();
                 self.send_action(Action::LoadActivePageContent)?;
             }
-            Action::EditActivePage => {
+            Action::ShowActivePageInfo => {
                 if let Some(page) = state.get_active_page() {
-                    let cmd = String::from("dnote");
-                    let cmd_args = vec!["edit".into(), page.id.to_string()];
-                    self.send_action(Action::ExecuteCommand(cmd, cmd_args))?;
-                    self.send_action(Action::UpdateActiveBookPages)?;
-                    self.send_action(Action::LoadActivePageContent)?;
+                    spawn_store_task(&self.store, &self.command_tx, move |store| {
+                        Ok(Action::ActivePageInfoLoaded(store.get_page(page.id)?))
+                    })?;
+                } else {
+                    log::error!("No active page to show info for");
+                }
+            }
+            Action::EditActivePage | Action::EditActivePageExternally => {
+                if let (Some(book), Some(page)) = (state.get_active_book(), state.get_active_page())
+                {
+                    let external = action == Action::EditActivePageExternally;
+                    spawn_store_task(&self.store, &self.command_tx, move |store| {
+                        let page_info = store.get_page_content(page.id)?;
+                        let draft = NoteDraft::edit_note(book.name, page.id, page_info.content);
+                        Ok(match external {
+                            true => Action::OpenExternalEditor(draft),
+                            false => Action::OpenEditor(draft),
+                        })
+                    })?;
                 } else {
                     log::error!("No active page to edit");
                 }
             }
+            Action::CyclePageSort if self.is_focused(state) => {
+                self.set_sort(state, PageSort::next_key)?
+            }
+            Action::ReversePageSort if self.is_focused(state) => {
+                self.set_sort(state, PageSort::reversed)?
+            }
+            Action::ToggleMark if self.is_focused(state) => {
+                state.pages.toggle_mark();
+                self.send_action(Action::SelectNextPage)?;
+            }
+            Action::ToggleVisualMode if self.is_focused(state) => state.pages.toggle_visual(),
+            Action::MarkAll if self.is_focused(state) => state.pages.mark_all(),
+            Action::ClearMarks if self.is_focused(state) => state.pages.clear_marks(),
+            Action::DeleteActivePage if !state.pages.marked_items().is_empty() => {
+                // Bulk deletes are always confirmed
+                let pages = state.get_target_pages();
+                let mut summaries: Vec<String> = pages
+                    .iter()
+                    .take(CONFIRM_SUMMARIES)
+                    .map(|page| format!("[{}] {}", page.id, page.summary))
+                    .collect();
+                if pages.len() > CONFIRM_SUMMARIES {
+                    summaries.push(format!("…and {} more", pages.len() - CONFIRM_SUMMARIES));
+                }
+                self.send_action(Action::Confirm {
+                    title: "Delete Notes".into(),
+                    message: format!(
+                        "Delete {}?\n\n{}",
+                        count_notes(pages.len()),
+                        summaries.join("\n")
+                    ),
+                    action: Box::new(Action::DeleteNotes(
+                        pages.iter().map(|page| page.id).collect(),
+                    )),
+                })?;
+            }
             Action::DeleteActivePage => {
                 if let Some(page) = state.get_active_page() {
-                    let cmd = String::from("dnote");
-                    let cmd_args = vec!["remove".into(), page.id.to_string()];
-                    self.send_action(Action::ExecuteCommand(cmd, cmd_args))?;
-                    self.send_action(Action::FocusPrev)?;
-                    self.send_action(Action::LoadActiveBookPages)?;
+                    let delete = Action::DeleteNote(page.id);
+                    match self.config.config.confirm_note_deletion {
+                        true => self.send_action(Action::Confirm {
+                            title: "Delete Note".into(),
+                            message: format!("Delete note {}?\n\n{}", page.id, page.summary),
+                            action: Box::new(delete),
+                        })?,
+                        false => self.send_action(delete)?,
+                    }
                 } else {
                     log::error!("No active page to delete");
                 }
@@ -119,19 +318,44 @@ fn update(&mut self, action: Action, state: &mut State) -> Result<Option<Action>
     }
 
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<()> {
+        let now = Utc::now();
+        let marked = state.pages.marked_items().len();
+        let visual = state.pages.visual_anchor.is_some();
+        // Leave room for mark indicators only while something is marked
+        let marking = marked > 0 || visual;
         let items: Vec<ListItem> = state
             .pages
             .items
             .iter()
-            .map(|i| {
+            .enumerate()
+            .map(|(index, i)| {
                 let _id = i.id.to_string();
                 let _summary = i.summary.to_string();
-                ListItem::new(Line::from(vec![
+                let mut spans = vec![];
+                if marking {
+                    spans.push(match state.pages.is_marked(index) {
+                        true => Span::styled("● ", Style::default().fg(Color::Yellow)),
+                        false => Span::raw("  "),
+                    });
+                }
+                spans.extend([
                     Span::styled("[", Style::default().fg(Color::White)),
                     Span::styled(_id, Style::default().fg(Color::Green)),
                     Span::styled("]", Style::default().fg(Color::White)),
                     Span::styled(format!(" {}", _summary), Style::default().fg(Color::White)),
-                ]))
+                ]);
+                let changed = match (i.edited_on, i.added_on) {
+                    (Some(edited_on), _) => Some(("edited", edited_on)),
+                    (None, Some(added_on)) => Some(("added", added_on)),
+                    (None, None) => None,
+                };
+                if let Some((verb, time)) = changed {
+                    spans.push(Span::styled(
+                        format!(" · {} {}", verb, format_relative_time(time, now)),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect();
         let total_items = items.len();
@@ -140,13 +364,27 @@ fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<(
             None => 0,
         };
         let title = Title::from(" Pages ".green().bold());
+        let title_loading = loading_title(state.loading.pages);
         let title_bottom =
             Line::from(format!(" {current_item_index} of {total_items} ")).right_aligned();
+        let title_filtered = filtered_title(&state.pages);
+        let title_marked = match (marked, visual) {
+            (0, false) => Line::default(),
+            (n, false) => Line::from(format!(" {n} marked ")),
+            (n, true) => Line::from(format!(" VISUAL {n} marked ")),
+        }
+        .left_aligned();
         let title_right = match state.get_active_book() {
-            Some(book) => Line::styled(
-                format!("[{}]", book.name),
-                Style::default().add_modifier(Modifier::ITALIC),
-            ),
+            Some(book) => Line::from(vec![
+                Span::styled(
+                    format!("{} ", self.sorts.get(&book.name).label()),
+                    Style::default().dark_gray(),
+                ),
+                Span::styled(
+                    format!("[{}]", book.name),
+                    Style::default().add_modifier(Modifier::ITALIC),
+                ),
+            ]),
             None => Line::default(),
         }
         .right_aligned();
@@ -161,16 +399,28 @@ fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<(
             .style(border_style)
             .title(title_padding.clone().left_aligned())
             .title(title)
+            .title(title_loading)
             .title(title_right)
+            .title_bottom(title_filtered.green())
+            .title_bottom(title_marked.yellow().bold())
             .title_bottom(title_bottom.green().bold())
             .title_bottom(title_padding.clone().right_aligned());
         let highlight_style = Style::default().on_black().bold();
+        let list_area = self
+            .filter
+            .draw(f, block.inner(area), state.pages.filter_query());
+        self.list_area = list_area;
+        f.render_widget(block, area);
         let list = List::new(items)
-            .block(block)
             .style(Style::default().white())
             // .highlight_symbol("→ ")
             .highlight_style(highlight_style);
-        f.render_stateful_widget(list, area, &mut state.pages.state);
+        f.render_stateful_widget(list, list_area, &mut state.pages.state);
         Ok(())
     }
 }
+
+/// Whether `page` matches a lowercased filter `query`, by summary or id.
+fn page_matches(page: &DnotePage, query: &str) -> bool {
+    page.summary.to_lowercase().contains(query) || page.id.to_string() == query
+}
@@ -3,15 +3,17 @@
 use ratatui::{
     prelude::*,
     symbols::border,
-    widgets::{Block, Borders, Clear, Padding, Paragraph},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Wrap},
 };
 use tokio::sync::mpsc::UnboundedSender;
 
 use super::{Component, Frame};
 use crate::{
-    action::Action,
+    action::{Action, NoteDraft},
     config::Config,
+    dnote::NoteId,
     state::{InputMode, State},
+    store,
 };
 use tui_input::backend::crossterm::EventHandler;
 use tui_input::Input;
@@ -22,6 +24,11 @@ pub enum PopupType {
     Normal,
     NewBook,
     RenameBook,
+    Info,
+    Error,
+    Confirm,
+    /// Asks where to export the notes.
+    ExportNotes(Vec<NoteId>),
 }
 
 #[derive(Default)]
@@ -30,7 +37,11 @@ pub struct Popup {
     input: Input,
     input_label: String,
     note: Option<String>,
+    error: Option<String>,
+    fields: Vec<(String, String)>,
     popup_type: PopupType,
+    /// Action run once a [`PopupType::Confirm`] popup is confirmed.
+    on_confirm: Option<Action>,
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
 }
@@ -54,6 +65,37 @@ pub fn new(
         }
     }
 
+    /// Create a popup showing an error `message`, e.g. a failed sync.
+    pub fn error(title: String, message: String) -> Self {
+        Self {
+            title,
+            note: Some(message),
+            popup_type: PopupType::Error,
+            ..Default::default()
+        }
+    }
+
+    /// Create a read-only popup listing `(label, value)` fields.
+    pub fn info(title: String, fields: Vec<(String, String)>) -> Self {
+        Self {
+            title,
+            fields,
+            popup_type: PopupType::Info,
+            ..Default::default()
+        }
+    }
+
+    /// Create a popup asking to confirm `message` before running `action`, e.g. a delete.
+    pub fn confirm(title: String, message: String, action: Action) -> Self {
+        Self {
+            title,
+            note: Some(message),
+            popup_type: PopupType::Confirm,
+            on_confirm: Some(action),
+            ..Default::default()
+        }
+    }
+
     fn send_action(&self, action: Action) -> Result<()> {
         if let Some(tx) = &self.command_tx {
             tx.send(action.clone())?;
@@ -62,6 +104,59 @@ fn send_action(&self, action: Action) -> Result<()> {
     }
 }
 
+impl Popup {
+    fn draw_error(&self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        let chunks = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+        let message = Paragraph::new(self.note.clone().unwrap_or_default())
+            .style(Style::default().white())
+            .wrap(Wrap { trim: false });
+        f.render_widget(message, chunks[0]);
+
+        let bottom_text = Paragraph::new("Press Esc or Enter to close")
+            .style(Style::default().dark_gray())
+            .alignment(Alignment::Center);
+        f.render_widget(bottom_text, chunks[1]);
+        Ok(())
+    }
+
+    fn draw_confirm(&self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        let chunks = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+        let message = Paragraph::new(self.note.clone().unwrap_or_default())
+            .style(Style::default().white())
+            .wrap(Wrap { trim: false });
+        f.render_widget(message, chunks[0]);
+
+        let bottom_text = Paragraph::new("Press y to confirm, n or Esc to cancel")
+            .style(Style::default().dark_gray())
+            .alignment(Alignment::Center);
+        f.render_widget(bottom_text, chunks[1]);
+        Ok(())
+    }
+
+    fn draw_info(&self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        let chunks = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+        let label_width = self.fields.iter().map(|(l, _)| l.len()).max().unwrap_or(0);
+        let lines: Vec<Line> = self
+            .fields
+            .iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    Span::styled(format!("{label:<label_width$}  "), Style::default().bold()),
+                    Span::styled(value.clone(), Style::default().white()),
+                ])
+            })
+            .collect();
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+        f.render_widget(paragraph, chunks[0]);
+
+        let bottom_text = Paragraph::new("Press Esc or Enter to close")
+            .style(Style::default().dark_gray())
+            .alignment(Alignment::Center);
+        f.render_widget(bottom_text, chunks[1]);
+        Ok(())
+    }
+}
+
 impl Component for Popup {
     fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
         self.command_tx = Some(tx);
@@ -82,33 +177,71 @@ fn handle_key_events(
             InputMode::Normal => Ok(None),
             InputMode::Insert => match key.code {
                 KeyCode::Enter => match self.popup_type {
+                    PopupType::Info | PopupType::Error => Ok(Some(Action::ClosePopup)),
                     PopupType::NewBook => {
-                        let book_name = self.input.value().to_string();
-                        let cmd = String::from("dnote");
-                        let cmd_args = vec!["add".into(), book_name];
-                        let action = Action::ExecuteCommand(cmd, cmd_args);
-                        self.send_action(action)?;
+                        let book_name = self.input.value().trim().to_string();
+                        if let Err(err) = store::validate_book_name(&book_name) {
+                            self.error = Some(err.to_string());
+                            return Ok(None);
+                        }
+                        // dnote books can't be empty, so a book is created with its first note
                         self.send_action(Action::ClosePopup)?;
-                        self.send_action(Action::LoadBooks)?;
+                        self.send_action(Action::OpenEditor(NoteDraft::new_note(book_name)))?;
                         Ok(None)
                     }
                     PopupType::RenameBook => {
                         if let Some(book) = state.get_active_book() {
-                            let old_name = book.name.clone();
-                            let new_name = self.input.value().to_string();
-                            let cmd = String::from("dnote");
-                            let cmd_args = vec!["edit".into(), old_name, "-n".into(), new_name];
-                            self.send_action(Action::ExecuteCommand(cmd, cmd_args))?;
+                            let new_name = self.input.value().trim().to_string();
+                            if let Err(err) = store::validate_book_name(&new_name) {
+                                self.error = Some(err.to_string());
+                                return Ok(None);
+                            }
                             self.send_action(Action::ClosePopup)?;
-                            self.send_action(Action::LoadBooks)?;
+                            self.send_action(Action::RenameBook {
+                                from: book.name,
+                                to: new_name,
+                            })?;
+                        }
+                        Ok(None)
+                    }
+                    PopupType::ExportNotes(ref ids) => {
+                        let path = self.input.value().trim();
+                        if path.is_empty() {
+                            self.error = Some("Path cannot be empty".into());
+                            return Ok(None);
                         }
+                        let export = Action::ExportNotes {
+                            ids: ids.clone(),
+                            path: path.into(),
+                        };
+                        self.send_action(Action::ClosePopup)?;
+                        self.send_action(export)?;
                         Ok(None)
                     }
                     _ => Ok(None),
                 },
                 KeyCode::Esc => Ok(Some(Action::ClosePopup)),
+                _ if matches!(self.popup_type, PopupType::Confirm) => match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        self.send_action(Action::ClosePopup)?;
+                        if let Some(action) = self.on_confirm.take() {
+                            self.send_action(action)?;
+                        }
+                        Ok(None)
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('q') => Ok(Some(Action::ClosePopup)),
+                    _ => Ok(None),
+                },
+                _ if matches!(self.popup_type, PopupType::Info | PopupType::Error) => {
+                    match key.code {
+                        KeyCode::Char('q') => Ok(Some(Action::ClosePopup)),
+                        _ => Ok(None),
+                    }
+                }
                 _ => {
-                    self.input.handle_event(&Event::Key(key));
+                    if self.input.handle_event(&Event::Key(key)).is_some() {
+                        self.error = None;
+                    }
                     Ok(None)
                 }
             },
@@ -125,14 +258,28 @@ fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<(
             .border_set(border::ROUNDED)
             .title_top(format!("> {} <", self.title.clone()))
             .title_alignment(Alignment::Center)
-            .padding(Padding::horizontal(4))
-            .style(Style::default().blue());
+            .padding(Padding::horizontal(match self.popup_type {
+                PopupType::Info | PopupType::Error | PopupType::Confirm => 1,
+                _ => 4,
+            }))
+            .style(match self.popup_type {
+                PopupType::Error => Style::default().red(),
+                PopupType::Confirm => Style::default().yellow(),
+                _ => Style::default().blue(),
+            });
 
         f.render_widget(outer_block.clone(), area);
 
         // Inner layout for the contents within the border
         let inner_area = outer_block.inner(area);
 
+        match self.popup_type {
+            PopupType::Info => return self.draw_info(f, inner_area),
+            PopupType::Error => return self.draw_error(f, inner_area),
+            PopupType::Confirm => return self.draw_confirm(f, inner_area),
+            _ => {}
+        }
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints(
@@ -173,7 +320,12 @@ fn draw(&mut self, f: &mut Frame<'_>, area: Rect, state: &mut State) -> Result<(
 
         f.render_widget(bottom_text, chunks[2]);
 
-        if let Some(note) = &self.note {
+        if let Some(error) = &self.error {
+            let error_paragraph = Paragraph::new(error.as_str())
+                .style(Style::default().red())
+                .alignment(Alignment::Center);
+            f.render_widget(error_paragraph, chunks[3]);
+        } else if let Some(note) = &self.note {
             let note_paragraph = Paragraph::new(note.as_str())
                 .style(Style::default().yellow())
                 .alignment(Alignment::Center);
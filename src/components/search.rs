@@ -0,0 +1,237 @@
+use color_eyre::eyre::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::*,
+    symbols::border,
+    widgets::{Block, Borders, Clear, List, ListItem, Padding, Paragraph},
+};
+use tokio::sync::mpsc::UnboundedSender;
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+
+use super::{loading_title, spawn_store_task, Component, Frame};
+use crate::{
+    action::Action,
+    config::Config,
+    search::{match_ranges, SearchHit},
+    state::{State, StatefulList},
+    store::SharedStore,
+};
+
+const STATUS_LINE: &str = "Type to search all notes  ↑/↓ Select  ⏎ Open  Esc Cancel";
+
+/// Popup searching the content of the notes of every book, see [`Mode::Search`](crate::state::Mode::Search).
+#[derive(Default)]
+pub struct SearchPopup {
+    input: Input,
+    results: StatefulList<SearchHit>,
+    /// The query `results` were found for.
+    results_query: String,
+    loading: bool,
+    store: Option<SharedStore>,
+    command_tx: Option<UnboundedSender<Action>>,
+    config: Config,
+}
+
+impl SearchPopup {
+    fn send_action(&self, action: Action) -> Result<()> {
+        if let Some(tx) = &self.command_tx {
+            tx.send(action.clone())?;
+        }
+        Ok(())
+    }
+
+    fn query(&self) -> String {
+        self.input.value().trim().to_string()
+    }
+
+    /// Search for the current query, results arrive as [`Action::SearchResultsLoaded`].
+    fn search(&mut self) -> Result<()> {
+        let query = self.query();
+        if query.is_empty() {
+            self.results = StatefulList::default();
+            self.results_query = query;
+            self.loading = false;
+            return Ok(());
+        }
+        spawn_store_task(&self.store, &self.command_tx, move |store| {
+            let hits = store.search(&query)?;
+            Ok(Action::SearchResultsLoaded(query, hits))
+        })?;
+        self.loading = true;
+        Ok(())
+    }
+
+    fn result_item(&self, hit: &SearchHit) -> ListItem<'static> {
+        let mut spans = vec![
+            Span::styled(format!("[{}]", hit.book_name), Style::default().blue()),
+            Span::styled(format!(" {} ", hit.id), Style::default().green()),
+        ];
+        let mut last = 0;
+        for range in match_ranges(&hit.snippet, &self.results_query) {
+            spans.push(Span::raw(hit.snippet[last..range.start].to_string()));
+            spans.push(Span::styled(
+                hit.snippet[range.clone()].to_string(),
+                Style::default().yellow().bold(),
+            ));
+            last = range.end;
+        }
+        spans.push(Span::raw(hit.snippet[last..].to_string()));
+        ListItem::new(Line::from(spans))
+    }
+}
+
+impl Component for SearchPopup {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn register_store_handler(&mut self, store: SharedStore) -> Result<()> {
+        self.store = Some(store);
+        Ok(())
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => Ok(Some(Action::ClosePopup)),
+            KeyCode::Enter => {
+                let Some(hit) = self
+                    .results
+                    .state
+                    .selected()
+                    .and_then(|i| self.results.items.get(i))
+                else {
+                    return Ok(None);
+                };
+                self.send_action(Action::ClosePopup)?;
+                self.send_action(Action::JumpToNote {
+                    book: hit.book_name.clone(),
+                    id: hit.id,
+                })?;
+                Ok(None)
+            }
+            KeyCode::Down => {
+                self.results.next();
+                Ok(None)
+            }
+            KeyCode::Char('n') if ctrl => {
+                self.results.next();
+                Ok(None)
+            }
+            KeyCode::Up => {
+                self.results.previous();
+                Ok(None)
+            }
+            KeyCode::Char('p') if ctrl => {
+                self.results.previous();
+                Ok(None)
+            }
+            _ => {
+                let changed = self
+                    .input
+                    .handle_event(&Event::Key(key))
+                    .is_some_and(|change| change.value);
+                if changed {
+                    self.search()?;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    fn update(&mut self, action: Action, _state: &mut State) -> Result<Option<Action>> {
+        match action {
+            Action::Tick => self.send_action(Action::StatusLine(STATUS_LINE.to_string()))?,
+            Action::SearchResultsLoaded(query, hits) => {
+                if query == self.query() {
+                    self.loading = false;
+                    self.results = StatefulList::with_items(hits);
+                    self.results.next();
+                    self.results_query = query;
+                } else {
+                    log::debug!("Discarding stale search results for {query}");
+                }
+            }
+            // The search failed, the error is shown in the footer
+            Action::Error(_) => self.loading = false,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect, _state: &mut State) -> Result<()> {
+        f.render_widget(Clear, area);
+
+        let outer_block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(border::ROUNDED)
+            .title_top("> Search <")
+            .title_alignment(Alignment::Center)
+            .padding(Padding::horizontal(1))
+            .style(Style::default().blue());
+        f.render_widget(outer_block.clone(), area);
+        let inner_area = outer_block.inner(area);
+
+        let chunks = Layout::vertical([
+            Constraint::Length(3), // Input field
+            Constraint::Fill(1),   // Results
+            Constraint::Length(1), // Bottom text
+        ])
+        .split(inner_area);
+
+        let width = chunks[0].width.max(3) - 3; // keep 2 for borders and 1 for cursor
+        let scroll = self.input.visual_scroll(width as usize);
+        let input = Paragraph::new(self.input.value())
+            .style(Style::default().yellow())
+            .scroll((0, scroll as u16))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(border::ROUNDED)
+                    .title("Query")
+                    .title(loading_title(self.loading)),
+            );
+        f.render_widget(input, chunks[0]);
+
+        let items: Vec<ListItem> = self
+            .results
+            .items
+            .iter()
+            .map(|hit| self.result_item(hit))
+            .collect();
+        let title_count = match (self.results_query.is_empty(), items.len()) {
+            (true, _) => Line::default(),
+            (false, 0) => Line::from(" No matches "),
+            (false, 1) => Line::from(" 1 match "),
+            (false, n) => Line::from(format!(" {n} matches ")),
+        }
+        .right_aligned();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::TOP)
+                    .title(title_count.blue().bold()),
+            )
+            .style(Style::default().white())
+            .highlight_style(Style::default().on_black().bold());
+        f.render_stateful_widget(list, chunks[1], &mut self.results.state);
+
+        let bottom_text = Paragraph::new("Press Esc to cancel, Enter to open the note")
+            .style(Style::default().dark_gray())
+            .alignment(Alignment::Center);
+        f.render_widget(bottom_text, chunks[2]);
+
+        f.set_cursor_position(Position {
+            x: chunks[0].x + ((self.input.visual_cursor()).max(scroll) - scroll) as u16 + 1,
+            y: chunks[0].y + 1,
+        });
+        Ok(())
+    }
+}
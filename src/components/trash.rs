@@ -0,0 +1,240 @@
+use chrono::{Local, Utc};
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    prelude::*,
+    symbols::border,
+    widgets::{Block, Borders, Clear, List, ListItem, Padding, Paragraph, Wrap},
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{format_relative_time, spawn_store_task, Component, Frame};
+use crate::{
+    action::Action,
+    config::Config,
+    state::{State, StatefulList},
+    store::SharedStore,
+    trash::{Trash, TrashEntry, Trashed},
+};
+
+const STATUS_LINE: &str = "↑/↓ Select  r/⏎ Restore  d Purge for good  Esc Close";
+
+/// Popup listing deleted notes and books, to restore or purge them.
+pub struct TrashPopup {
+    trash: Trash,
+    entries: StatefulList<TrashEntry>,
+    error: Option<String>,
+    store: Option<SharedStore>,
+    command_tx: Option<UnboundedSender<Action>>,
+    config: Config,
+}
+
+impl TrashPopup {
+    pub fn new(trash: Trash) -> Self {
+        Self {
+            trash,
+            entries: StatefulList::with_items(Vec::new()),
+            error: None,
+            store: None,
+            command_tx: None,
+            config: Config::default(),
+        }
+    }
+
+    fn send_action(&self, action: Action) -> Result<()> {
+        if let Some(tx) = &self.command_tx {
+            tx.send(action.clone())?;
+        }
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&TrashEntry> {
+        self.entries
+            .state
+            .selected()
+            .and_then(|i| self.entries.items.get(i))
+    }
+
+    /// Reload the entries, keeping the selection in place.
+    fn load(&mut self) -> Result<()> {
+        let selected = self.entries.state.selected();
+        self.entries = StatefulList::with_items(self.trash.list()?);
+        match selected {
+            Some(i) if !self.entries.items.is_empty() => {
+                self.entries
+                    .state
+                    .select(Some(i.min(self.entries.items.len() - 1)));
+            }
+            _ => self.entries.next(),
+        }
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<()> {
+        let Some(entry) = self.selected() else {
+            return Ok(());
+        };
+        let (trash, id) = (self.trash.clone(), entry.id.clone());
+        spawn_store_task(&self.store, &self.command_tx, move |store| {
+            trash.restore(store, &id)
+        })?;
+        self.send_action(Action::ClosePopup)
+    }
+
+    fn purge(&mut self) -> Result<()> {
+        let Some(entry) = self.selected() else {
+            return Ok(());
+        };
+        self.send_action(Action::Confirm {
+            title: "Purge".into(),
+            message: format!(
+                "Delete {} for good? It can't be restored.",
+                entry.describe()
+            ),
+            action: Box::new(Action::PurgeTrash(entry.id.clone())),
+        })
+    }
+
+    fn preview_text(&self) -> Text<'static> {
+        let Some(entry) = self.selected() else {
+            return Text::default();
+        };
+        match &entry.item {
+            Trashed::Note(note) => Text::from(note.content.clone()),
+            Trashed::Book { notes, .. } => notes
+                .iter()
+                .map(|note| {
+                    let summary = note.content.lines().next().unwrap_or_default().to_string();
+                    Line::from(vec![
+                        Span::styled(format!("[{}]", note.id), Style::default().green()),
+                        Span::raw(format!(" {summary}")),
+                    ])
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Component for TrashPopup {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn register_store_handler(&mut self, store: SharedStore) -> Result<()> {
+        self.store = Some(store);
+        Ok(())
+    }
+
+    fn init(&mut self, _area: Size) -> Result<()> {
+        let retention_days = self.config.config.trash_retention_days;
+        match self.trash.purge_expired(retention_days, Utc::now()) {
+            Ok(0) => {}
+            Ok(n) => log::info!("Purged {n} trash entries older than {retention_days} days"),
+            Err(err) => log::error!("Failed to purge expired trash entries: {err}"),
+        }
+        if let Err(err) = self.load() {
+            self.error = Some(err.to_string());
+        }
+        Ok(())
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent, _state: &mut State) -> Result<Option<Action>> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(Some(Action::ClosePopup)),
+            KeyCode::Down | KeyCode::Char('j') => self.entries.next(),
+            KeyCode::Up | KeyCode::Char('k') => self.entries.previous(),
+            KeyCode::Enter | KeyCode::Char('r') => self.restore()?,
+            KeyCode::Char('d') => self.purge()?,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action, _state: &mut State) -> Result<Option<Action>> {
+        if action == Action::Tick {
+            self.send_action(Action::StatusLine(STATUS_LINE.to_string()))?;
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect, _state: &mut State) -> Result<()> {
+        f.render_widget(Clear, area);
+
+        let outer_block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(border::ROUNDED)
+            .title_top("> Trash <")
+            .title_alignment(Alignment::Center)
+            .padding(Padding::horizontal(1))
+            .style(Style::default().blue());
+        f.render_widget(outer_block.clone(), area);
+        let inner_area = outer_block.inner(area);
+
+        let columns = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .spacing(1)
+            .split(inner_area);
+        let rows = Layout::vertical([
+            Constraint::Fill(1),   // Entries
+            Constraint::Length(1), // Bottom text
+        ])
+        .split(columns[0]);
+
+        let now = Utc::now();
+        let items: Vec<ListItem> = self
+            .entries
+            .items
+            .iter()
+            .map(|entry| {
+                let deleted_at = entry.deleted_at.with_timezone(&Local);
+                ListItem::new(Line::from(vec![
+                    Span::styled(entry.describe(), Style::default().white()),
+                    Span::styled(
+                        format!(
+                            " · {} ({})",
+                            deleted_at.format("%Y-%m-%d %H:%M"),
+                            format_relative_time(entry.deleted_at, now)
+                        ),
+                        Style::default().dark_gray(),
+                    ),
+                ]))
+            })
+            .collect();
+        let title_count = Line::from(format!(" {} deleted ", items.len())).right_aligned();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(border::ROUNDED)
+                    .title("Deleted")
+                    .title(title_count.blue().bold()),
+            )
+            .highlight_style(Style::default().on_black().bold());
+        f.render_stateful_widget(list, rows[0], &mut self.entries.state);
+
+        let bottom_text = match &self.error {
+            Some(error) => Paragraph::new(error.as_str()).style(Style::default().red()),
+            None => Paragraph::new("Press r to restore, d to purge, Esc to close")
+                .style(Style::default().dark_gray()),
+        }
+        .alignment(Alignment::Center);
+        f.render_widget(bottom_text, rows[1]);
+
+        let preview = Paragraph::new(self.preview_text())
+            .style(Style::default().white())
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(border::ROUNDED)
+                    .title("Preview"),
+            );
+        f.render_widget(preview, columns[1]);
+        Ok(())
+    }
+}
@@ -9,17 +9,155 @@
 use serde::{de::Deserializer, Deserialize};
 use tracing::error;
 
-use crate::{action::Action, state::Mode};
+use crate::{action::Action, highlight, state::Mode};
 use ratatui::symbols::scrollbar;
 
 const CONFIG: &str = include_str!("../.config/config.json5");
 
-#[derive(Clone, Debug, Deserialize, Default)]
+/// The note store backend used to read and write books and notes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Shell out to the `dnote` CLI.
+    #[default]
+    Cli,
+    /// Query the dnote CLI's SQLite database directly.
+    Sqlite,
+}
+
+/// Extra column shown after the name of each book in the books pane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookColumn {
+    /// Number of notes in the book.
+    Notes,
+    /// How long ago a note in the book was last added or edited, e.g. `3d`.
+    Edited,
+}
+
+/// How the `dnote` CLI is invoked.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct DnoteConfig {
+    /// Path to the `dnote` executable, looked up on `PATH` if it is a bare name.
+    #[serde(default = "DnoteConfig::default_bin")]
+    pub bin: PathBuf,
+    /// Global arguments passed before every subcommand, e.g. `["--dbPath", "/path/to/dnote.db"]`.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Environment overrides for dnote, e.g. `{ "XDG_DATA_HOME": "/path/to/work" }`.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Directory dnote is run in, the app's own working directory if unset.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+}
+
+impl DnoteConfig {
+    fn default_bin() -> PathBuf {
+        PathBuf::from("dnote")
+    }
+
+    /// Looks up an environment variable, preferring the configured overrides.
+    pub fn env_var(&self, key: &str) -> Option<String> {
+        self.env
+            .get(key)
+            .cloned()
+            .or_else(|| env::var(key).ok())
+            .filter(|v| !v.is_empty())
+    }
+}
+
+impl Default for DnoteConfig {
+    fn default() -> Self {
+        Self {
+            bin: Self::default_bin(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            dir: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
 pub struct AppConfig {
     #[serde(default)]
     pub data_dir: PathBuf,
     #[serde(default)]
     pub config_dir: PathBuf,
+    #[serde(default)]
+    pub backend: Backend,
+    /// Path to `dnote.db`, defaults to the dnote CLI's own database location.
+    #[serde(default)]
+    pub dnote_db_path: Option<PathBuf>,
+    #[serde(default)]
+    pub dnote: DnoteConfig,
+    /// Cache books, page lists and note contents between reads.
+    #[serde(default = "AppConfig::default_cache")]
+    pub cache: bool,
+    /// Command used to edit notes externally, defaults to `$VISUAL` or `$EDITOR`.
+    #[serde(default)]
+    pub editor: Option<String>,
+    /// Add and edit notes in the external editor instead of the built-in one.
+    #[serde(default)]
+    pub external_editor: bool,
+    /// Ask before deleting a single note, books are always confirmed.
+    #[serde(default = "AppConfig::default_confirm_note_deletion")]
+    pub confirm_note_deletion: bool,
+    /// Theme highlighting code blocks in notes, one of syntect's bundled themes.
+    #[serde(default = "AppConfig::default_syntax_theme")]
+    pub syntax_theme: String,
+    /// Days deleted notes and books are kept in the trash, 0 keeps them forever.
+    #[serde(default = "AppConfig::default_trash_retention_days")]
+    pub trash_retention_days: u32,
+    /// Columns shown after book names, dropped from the right when the pane is too narrow.
+    #[serde(default = "AppConfig::default_book_columns")]
+    pub book_columns: Vec<BookColumn>,
+    /// Click and scroll the panes with the mouse. Capturing the mouse keeps the terminal from
+    /// selecting text, usually unless Shift is held.
+    #[serde(default)]
+    pub mouse: bool,
+}
+
+impl AppConfig {
+    fn default_cache() -> bool {
+        true
+    }
+
+    fn default_confirm_note_deletion() -> bool {
+        true
+    }
+
+    fn default_syntax_theme() -> String {
+        highlight::DEFAULT_THEME.to_string()
+    }
+
+    fn default_trash_retention_days() -> u32 {
+        30
+    }
+
+    fn default_book_columns() -> Vec<BookColumn> {
+        vec![BookColumn::Notes, BookColumn::Edited]
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::default(),
+            config_dir: PathBuf::default(),
+            backend: Backend::default(),
+            dnote_db_path: None,
+            dnote: DnoteConfig::default(),
+            cache: Self::default_cache(),
+            editor: None,
+            external_editor: false,
+            confirm_note_deletion: Self::default_confirm_note_deletion(),
+            syntax_theme: Self::default_syntax_theme(),
+            trash_retention_days: Self::default_trash_retention_days(),
+            book_columns: Self::default_book_columns(),
+            mouse: false,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -402,6 +540,8 @@ fn process_color_string(color_str: &str) -> (String, Modifier) {
         .replace("bright ", "")
         .replace("bold ", "")
         .replace("underline ", "")
+        .replace("italic ", "")
+        .replace("strikethrough ", "")
         .replace("inverse ", "");
 
     let mut modifiers = Modifier::empty();
@@ -414,6 +554,12 @@ fn process_color_string(color_str: &str) -> (String, Modifier) {
     if color_str.contains("inverse") {
         modifiers |= Modifier::REVERSED;
     }
+    if color_str.contains("italic") {
+        modifiers |= Modifier::ITALIC;
+    }
+    if color_str.contains("strikethrough") {
+        modifiers |= Modifier::CROSSED_OUT;
+    }
 
     (color, modifiers)
 }
@@ -485,9 +631,12 @@ fn parse_color(s: &str) -> Option<Color> {
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::dnote::Dnote;
 
     #[test]
     fn test_parse_style_default() {
@@ -514,6 +663,16 @@ fn test_parse_style_modifiers() {
         assert_eq!(style.bg, Some(Color::Indexed(4)));
     }
 
+    #[test]
+    fn test_parse_style_italic_and_strikethrough() {
+        let style = parse_style("italic strikethrough green");
+        assert_eq!(style.fg, Some(Color::Indexed(2)));
+        assert!(style
+            .add_modifier
+            .contains(Modifier::ITALIC | Modifier::CROSSED_OUT));
+        assert_eq!(parse_style("italic").add_modifier, Modifier::ITALIC);
+    }
+
     #[test]
     fn test_process_color_string() {
         let (color, modifiers) = process_color_string("underline bold inverse gray");
@@ -550,6 +709,54 @@ fn test_config() -> Result<()> {
         Ok(())
     }
 
+    #[test]
+    fn test_dnote_config() {
+        let c: AppConfig = json5::from_str(
+            r#"{ "dnote": { "bin": "/opt/dnote", "env": { "XDG_DATA_HOME": "/tmp/work" }, "dir": "/tmp/work" } }"#,
+        )
+        .unwrap();
+        assert_eq!(c.dnote.bin, PathBuf::from("/opt/dnote"));
+        assert!(c.dnote.args.is_empty());
+        assert_eq!(
+            c.dnote.env_var("XDG_DATA_HOME").as_deref(),
+            Some("/tmp/work")
+        );
+        assert_eq!(
+            Dnote::command(&c.dnote).get_current_dir(),
+            Some(Path::new("/tmp/work"))
+        );
+        assert_eq!(AppConfig::default().dnote.bin, PathBuf::from("dnote"));
+        assert_eq!(
+            Dnote::command(&AppConfig::default().dnote).get_current_dir(),
+            None
+        );
+        assert!(c.cache);
+        assert!(!c.mouse);
+    }
+
+    #[test]
+    fn test_status_line_orders_mark_keys() -> Result<()> {
+        let c = Config::new()?;
+        let status_line = build_status_line(&c, &Mode::Page);
+        let position = |label: &str| status_line.find(&format!("[{label} ")).unwrap();
+        assert!(position("Mark") < position("Visual"));
+        assert!(position("Visual") < position("Mark All"));
+        assert!(position("Mark All") < position("Unmark"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_book_columns() -> Result<()> {
+        let c = Config::new()?;
+        assert_eq!(
+            c.config.book_columns,
+            vec![BookColumn::Notes, BookColumn::Edited]
+        );
+        let c: AppConfig = json5::from_str(r#"{ "book_columns": ["edited"] }"#).unwrap();
+        assert_eq!(c.book_columns, vec![BookColumn::Edited]);
+        Ok(())
+    }
+
     #[test]
     fn test_simple_keys() {
         assert_eq!(
@@ -1,56 +1,180 @@
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Result};
-use std::{process::Command, str::FromStr};
+use serde::{Deserialize, Serialize};
+use std::{fmt, io, process::Command, str::FromStr, string::FromUtf8Error};
 
-type NoteId = u32;
+use crate::{
+    config::DnoteConfig,
+    search::{self, SearchHit},
+    store::NoteStore,
+};
 
-#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub mod parser;
+
+pub type NoteId = u32;
+
+/// Errors that can occur while running the `dnote` CLI and reading its output.
+#[derive(Debug)]
+pub enum DnoteError {
+    /// The `dnote` executable could not be found.
+    BinaryNotFound(String),
+    /// The `dnote` executable could not be spawned for another reason.
+    Io(io::Error),
+    /// `dnote` exited with a non-zero status.
+    CommandFailed {
+        command: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+    /// A line of `dnote` output did not have the expected format.
+    Parse { line: String },
+    /// `dnote` output was not valid UTF-8.
+    Utf8(FromUtf8Error),
+}
+
+impl fmt::Display for DnoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnoteError::BinaryNotFound(bin) => {
+                write!(f, "dnote executable `{bin}` not found, is dnote installed?")
+            }
+            DnoteError::Io(err) => write!(f, "Failed to run dnote: {err}"),
+            DnoteError::CommandFailed {
+                command,
+                code,
+                stderr,
+            } => {
+                let code = code.map_or("signal".to_string(), |c| c.to_string());
+                write!(f, "`{command}` failed with exit code {code}: {stderr}")
+            }
+            DnoteError::Parse { line } => write!(f, "Unable to parse dnote output: `{line}`"),
+            DnoteError::Utf8(err) => write!(f, "dnote output is not valid UTF-8: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DnoteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DnoteError::Io(err) => Some(err),
+            DnoteError::Utf8(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// A book with what the active backend knows of its notes, e.g. `dnote view` prints counts but
+/// no dates.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DnoteBook {
     pub name: String,
+    pub note_count: Option<usize>,
+    /// When a note in the book was last added or edited.
+    pub last_edited: Option<DateTime<Utc>>,
+}
+
+impl DnoteBook {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
 }
 
 impl FromStr for DnoteBook {
-    type Err = color_eyre::Report;
-    fn from_str(s: &str) -> Result<Self> {
-        let name = s.trim().to_string();
-        Ok(DnoteBook { name })
+    type Err = DnoteError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parser::parse_book_line(s)
     }
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+/// A note as listed in a book. Metadata that the active backend can't provide is `None`, e.g.
+/// `dnote view <book>` only prints ids and summaries.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DnotePage {
     pub id: NoteId,
     pub summary: String,
+    pub uuid: Option<String>,
+    pub book_name: Option<String>,
+    pub added_on: Option<DateTime<Utc>>,
+    pub edited_on: Option<DateTime<Utc>>,
+    pub public: Option<bool>,
+}
+
+impl DnotePage {
+    /// When the note was last changed, i.e. edited or else added.
+    pub fn last_changed(&self) -> Option<DateTime<Utc>> {
+        self.edited_on.or(self.added_on)
+    }
+}
+
+/// Converts a dnote timestamp (unix nanoseconds, 0 meaning unset) to a date time.
+pub fn from_dnote_timestamp(nanos: i64) -> Option<DateTime<Utc>> {
+    match nanos {
+        0 => None,
+        nanos => Some(DateTime::from_timestamp_nanos(nanos)),
+    }
 }
 
 impl FromStr for DnotePage {
-    type Err = color_eyre::Report;
-    fn from_str(s: &str) -> Result<Self> {
-        let parts: Vec<&str> = s.split(')').collect();
-        let id = parts[0]
-            .trim()
-            .trim_start_matches('(')
-            .parse()
-            .map_err(|_| eyre!("Parsing error"))?;
-        let summary = parts[1]
-            .trim()
-            .trim_end_matches("[---More---]")
-            .trim()
-            .to_string();
-        Ok(DnotePage { id, summary })
+    type Err = DnoteError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parser::parse_page_line(s)
     }
 }
 
+/// Returns the summary of a note body the way `dnote view <book>` prints it, i.e. its first line.
+pub fn summarize(content: &str) -> String {
+    content
+        .trim_end_matches(['\r', '\n'])
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
 #[derive(Debug, Clone)]
 pub struct DnotePageInfo {
     pub content: String,
 }
 
 impl FromStr for DnotePageInfo {
-    type Err = color_eyre::Report;
-    fn from_str(s: &str) -> Result<Self> {
-        let content = s.trim().to_string();
-        Ok(DnotePageInfo { content })
+    type Err = DnoteError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(parser::parse_page_content(s))
+    }
+}
+
+/// Turns a failed `dnote sync` into a message that tells the user what to do about it.
+pub fn describe_sync_error(err: &DnoteError) -> String {
+    let DnoteError::CommandFailed { stderr, .. } = err else {
+        return err.to_string();
+    };
+    let output = stderr.to_lowercase();
+    if output.contains("not logged in") || output.contains("login required") {
+        "You are not logged in to a dnote server. Run `dnote login` in a terminal, then sync again."
+            .to_string()
+    } else if output.contains("conflict") {
+        format!("Sync ran into a conflict:\n\n{stderr}\n\nResolve it by running `dnote sync` in a terminal.")
+    } else {
+        format!("Sync failed:\n\n{stderr}")
+    }
+}
+
+/// The command line of a dnote call for error messages, with note content elided so it doesn't
+/// end up in logs.
+fn describe_command(bin: &str, cmd: &str, args: &[String]) -> String {
+    let mut words = vec![bin, cmd];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        words.push(arg);
+        if arg == "-c" && args.next().is_some() {
+            words.push("<content>");
+        }
     }
+    words.join(" ")
 }
 
 #[derive(Debug)]
@@ -66,6 +190,9 @@ pub enum DnoteCommand {
     ViewByNoteId {
         note_id: NoteId,
     },
+    ViewNoteInfoById {
+        note_id: NoteId,
+    },
     EditNoteById {
         note_id: String,
         new_content: Option<String>,
@@ -81,27 +208,48 @@ pub enum DnoteCommand {
     RemoveNoteById {
         note_id: NoteId,
     },
+    Find {
+        query: String,
+    },
+    Sync,
 }
 
 #[derive(Debug, Default)]
-pub struct Dnote {}
+pub struct Dnote {
+    config: DnoteConfig,
+}
 
 impl Dnote {
     pub fn new() -> Self {
         Self::default()
     }
 
-    fn execute_command(&self, command: DnoteCommand) -> Result<String> {
+    pub fn with_config(config: DnoteConfig) -> Self {
+        Self { config }
+    }
+
+    /// A `dnote` command with the configured global arguments and environment applied.
+    pub fn command(config: &DnoteConfig) -> Command {
+        let mut command = Command::new(&config.bin);
+        command.args(&config.args).envs(&config.env);
+        if let Some(dir) = &config.dir {
+            command.current_dir(dir);
+        }
+        command
+    }
+
+    fn execute_command(&self, command: DnoteCommand) -> Result<String, DnoteError> {
         let (cmd, args) = match command {
             DnoteCommand::Add { book_name, note } => {
                 ("add", vec![book_name, "-c".to_string(), note])
             }
-            DnoteCommand::ViewBooks => ("view", vec!["--name-only".to_string()]),
+            DnoteCommand::ViewBooks => ("view", vec![]),
             DnoteCommand::ViewByBook { book_name } => ("view", vec![book_name]),
             DnoteCommand::ViewByNoteId { note_id } => (
                 "view",
                 vec![note_id.to_string(), "--content-only".to_string()],
             ),
+            DnoteCommand::ViewNoteInfoById { note_id } => ("view", vec![note_id.to_string()]),
             DnoteCommand::EditNoteById {
                 note_id,
                 new_content,
@@ -129,41 +277,125 @@ fn execute_command(&self, command: DnoteCommand) -> Result<String> {
                 }
                 ("edit", args)
             }
-            DnoteCommand::RemoveBook { book_name } => ("rm", vec![book_name]),
-            DnoteCommand::RemoveNoteById { note_id } => ("rm", vec![note_id.to_string()]),
+            // `-y` skips dnote's confirmation prompt, which can't be answered without a tty
+            DnoteCommand::RemoveBook { book_name } => ("rm", vec![book_name, "-y".to_string()]),
+            DnoteCommand::RemoveNoteById { note_id } => {
+                ("rm", vec![note_id.to_string(), "-y".to_string()])
+            }
+            DnoteCommand::Find { query } => ("find", vec![query]),
+            DnoteCommand::Sync => ("sync", vec![]),
         };
-        let output = Command::new("dnote").arg(cmd).args(args).output()?;
-        let stdout = String::from_utf8(output.stdout)?;
-        Ok(stdout)
+        let bin = self.config.bin.display().to_string();
+        let output = Self::command(&self.config)
+            .arg(cmd)
+            .args(&args)
+            .output()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => DnoteError::BinaryNotFound(bin.clone()),
+                _ => DnoteError::Io(err),
+            })?;
+        if !output.status.success() {
+            // dnote reports some errors on stdout, fall back to it when stderr is empty
+            let stderr = match String::from_utf8_lossy(&output.stderr).trim() {
+                "" => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+                stderr => stderr.to_string(),
+            };
+            return Err(DnoteError::CommandFailed {
+                command: describe_command(&bin, cmd, &args),
+                code: output.status.code(),
+                stderr,
+            });
+        }
+        String::from_utf8(output.stdout).map_err(DnoteError::Utf8)
     }
+}
 
-    pub fn get_books(&self) -> Result<Vec<DnoteBook>> {
+impl NoteStore for Dnote {
+    fn get_books(&self) -> Result<Vec<DnoteBook>> {
         let output = self.execute_command(DnoteCommand::ViewBooks)?;
-        output.lines().map(|l| l.parse()).collect()
+        Ok(parser::parse_books(&output)?)
+    }
+
+    fn get_pages(&self, book_name: &str) -> Result<Vec<DnotePage>> {
+        let output = self.execute_command(DnoteCommand::ViewByBook {
+            book_name: book_name.to_string(),
+        })?;
+        Ok(parser::parse_pages(&output)?)
     }
 
-    pub fn rename_book(&self, book_name: &str, new_book_name: &str) -> Result<()> {
+    fn get_page_content(&self, page_id: NoteId) -> Result<DnotePageInfo> {
+        let output = self.execute_command(DnoteCommand::ViewByNoteId { note_id: page_id })?;
+        Ok(parser::parse_page_content(&output))
+    }
+
+    fn get_page(&self, page_id: NoteId) -> Result<DnotePage> {
+        let output = self.execute_command(DnoteCommand::ViewNoteInfoById { note_id: page_id })?;
+        Ok(parser::parse_page_info(&output)?)
+    }
+
+    fn add_note(&self, book_name: &str, content: &str) -> Result<()> {
+        self.execute_command(DnoteCommand::Add {
+            book_name: book_name.to_string(),
+            note: content.to_string(),
+        })?;
+        Ok(())
+    }
+
+    fn edit_note(&self, page_id: NoteId, content: &str) -> Result<()> {
+        self.execute_command(DnoteCommand::EditNoteById {
+            note_id: page_id.to_string(),
+            new_content: Some(content.to_string()),
+            new_book: None,
+        })?;
+        Ok(())
+    }
+
+    fn move_note(&self, page_id: NoteId, book_name: &str) -> Result<()> {
+        self.execute_command(DnoteCommand::EditNoteById {
+            note_id: page_id.to_string(),
+            new_content: None,
+            new_book: Some(book_name.to_string()),
+        })?;
+        Ok(())
+    }
+
+    fn remove_note(&self, page_id: NoteId) -> Result<()> {
+        self.execute_command(DnoteCommand::RemoveNoteById { note_id: page_id })?;
+        Ok(())
+    }
+
+    fn rename_book(&self, book_name: &str, new_name: &str) -> Result<()> {
         self.execute_command(DnoteCommand::EditBook {
             book_name: book_name.to_string(),
-            new_name: Some(new_book_name.to_string()),
+            new_name: Some(new_name.to_string()),
         })?;
         Ok(())
     }
 
-    pub fn get_pages(&self, book_name: &str) -> Result<Vec<DnotePage>> {
-        let output = self.execute_command(DnoteCommand::ViewByBook {
+    fn remove_book(&self, book_name: &str) -> Result<()> {
+        self.execute_command(DnoteCommand::RemoveBook {
             book_name: book_name.to_string(),
         })?;
-        output
-            .lines()
-            .skip(1) // skip first line e.g '  • on book ccu'
-            .map(|l| l.parse())
-            .collect()
+        Ok(())
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let output = self.execute_command(DnoteCommand::Find {
+            query: query.to_string(),
+        })?;
+        let mut hits = parser::parse_search_results(&output)?;
+        hits.truncate(search::MAX_RESULTS);
+        Ok(hits)
     }
 
-    pub fn get_page_content(&self, page_id: NoteId) -> Result<DnotePageInfo> {
-        let output = self.execute_command(DnoteCommand::ViewByNoteId { note_id: page_id })?;
-        output.parse()
+    fn sync(&self) -> Result<()> {
+        self.execute_command(DnoteCommand::Sync)
+            .map_err(|err| eyre!(describe_sync_error(&err)))?;
+        Ok(())
     }
 }
 
@@ -175,7 +407,10 @@ mod tests {
     fn should_parse_dnotebook_from_string() {
         let s = String::from("my notebook");
         let book: DnoteBook = s.parse().unwrap();
-        assert_eq!(book.name, "my notebook")
+        assert_eq!(book.name, "my notebook");
+        let book: DnoteBook = "  • my notebook (12)".parse().unwrap();
+        assert_eq!(book.name, "my notebook");
+        assert_eq!(book.note_count, Some(12));
     }
 
     #[test]
@@ -190,6 +425,47 @@ fn should_parse_dnotepage_from_string() {
         assert_eq!(page2.summary, "# Missed");
     }
 
+    #[test]
+    fn should_fail_to_parse_malformed_dnotepage_with_offending_line() {
+        for input in ["no id here", "(abc) summary", ""] {
+            match input.parse::<DnotePage>() {
+                Err(DnoteError::Parse { line }) => assert_eq!(line, input),
+                other => panic!("expected parse error for {input:?}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn should_display_dnote_errors() {
+        let err = DnoteError::CommandFailed {
+            command: "dnote view nope".into(),
+            code: Some(1),
+            stderr: "book not found".into(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "`dnote view nope` failed with exit code 1: book not found"
+        );
+        let err = DnoteError::BinaryNotFound("dnote".into());
+        assert_eq!(
+            err.to_string(),
+            "dnote executable `dnote` not found, is dnote installed?"
+        );
+    }
+
+    #[test]
+    fn should_elide_note_content_from_commands() {
+        let args = ["rust", "-c", "secret\nstuff", "-b", "go"].map(String::from);
+        assert_eq!(
+            describe_command("dnote", "edit", &args),
+            "dnote edit rust -c <content> -b go"
+        );
+        assert_eq!(
+            describe_command("dnote", "view", &["rust".to_string()]),
+            "dnote view rust"
+        );
+    }
+
     #[test]
     fn should_parse_dnotepageinfo_from_string() {
         let input1 = "# E2E\n\n- Grab a list of all data test ids on a page\n- Make sure all those data test ids exist";
@@ -202,4 +478,33 @@ fn should_parse_dnotepageinfo_from_string() {
         assert_eq!(page_info2.content, input2.trim());
         assert_eq!(page_info3.content, input3);
     }
+
+    #[test]
+    fn should_summarize_note_content_as_first_line() {
+        assert_eq!(summarize("  # Title  \n\nbody"), "# Title");
+        assert_eq!(summarize("single line\n"), "single line");
+        assert_eq!(summarize(""), "");
+    }
+
+    #[test]
+    fn should_describe_sync_errors() {
+        let failed = |stderr: &str| DnoteError::CommandFailed {
+            command: "dnote sync".into(),
+            code: Some(1),
+            stderr: stderr.into(),
+        };
+        assert!(describe_sync_error(&failed("Error: not logged in")).contains("dnote login"));
+        assert!(describe_sync_error(&failed("Error: login required")).contains("dnote login"));
+        let conflict = describe_sync_error(&failed("merge conflict in note abc"));
+        assert!(conflict.starts_with("Sync ran into a conflict"));
+        assert!(conflict.contains("merge conflict in note abc"));
+        assert_eq!(
+            describe_sync_error(&failed("server unreachable")),
+            "Sync failed:\n\nserver unreachable"
+        );
+        assert_eq!(
+            describe_sync_error(&DnoteError::BinaryNotFound("dnote".into())),
+            "dnote executable `dnote` not found, is dnote installed?"
+        );
+    }
 }
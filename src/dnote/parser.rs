@@ -0,0 +1,379 @@
+//! Parsers for the human-oriented output of `dnote view` and `dnote find`.
+//!
+//! dnote colors its output when it thinks it is writing to a terminal, so every line is stripped
+//! of ANSI escapes before it is parsed.
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+
+use super::{summarize, DnoteBook, DnoteError, DnotePage, DnotePageInfo};
+use crate::search::SearchHit;
+
+/// Marker dnote appends to a note summary when the note has more than one line.
+const MORE_MARKER: &str = "[---More---]";
+
+/// Bullet dnote prefixes informational lines with, e.g. `  • on book rust`.
+const INFO_BULLET: char = '•';
+
+/// Dashes that open and close the note body in `dnote view <id>`.
+const CONTENT_SEPARATOR: &str = "-----";
+
+/// Go layout `Jan 2, 2006 3:04pm` used by dnote for timestamps, without the zone abbreviation.
+const TIME_FORMAT: &str = "%b %d, %Y %I:%M%p";
+
+fn clean_line(line: &str) -> String {
+    strip_ansi_escapes::strip_str(line).trim().to_string()
+}
+
+fn is_info_line(line: &str) -> bool {
+    line.starts_with(INFO_BULLET)
+}
+
+/// Parse the output of `dnote view`, one `• <name> (<count>)` line per book. The plain names
+/// printed by `dnote view --name-only` are accepted too, without counts.
+pub fn parse_books(output: &str) -> Result<Vec<DnoteBook>, DnoteError> {
+    output
+        .lines()
+        .map(clean_line)
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_book_line(&line))
+        .collect()
+}
+
+/// Parse a single `• <name> (<count>)` or bare `<name>` book line.
+pub fn parse_book_line(line: &str) -> Result<DnoteBook, DnoteError> {
+    let cleaned = clean_line(line);
+    let cleaned = cleaned.strip_prefix(INFO_BULLET).unwrap_or(&cleaned).trim();
+    let counted = cleaned
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once(" ("))
+        .and_then(|(name, count)| Some((name.trim_end(), count.parse().ok()?)));
+    let book = match counted {
+        Some((name, count)) => DnoteBook {
+            note_count: Some(count),
+            ..DnoteBook::new(name)
+        },
+        None => DnoteBook::new(cleaned),
+    };
+    if book.name.is_empty() {
+        return Err(DnoteError::Parse {
+            line: line.to_string(),
+        });
+    }
+    Ok(book)
+}
+
+/// Parse the output of `dnote view <book>`: an `on book <name>` header followed by one
+/// `(<id>) <summary>` line per note. An empty book only prints the header.
+pub fn parse_pages(output: &str) -> Result<Vec<DnotePage>, DnoteError> {
+    output
+        .lines()
+        .map(clean_line)
+        .filter(|line| !line.is_empty() && !is_info_line(line))
+        .map(|line| parse_page_line(&line))
+        .collect()
+}
+
+/// Parse a single `(<id>) <summary> [---More---]` note line.
+///
+/// Only the leading `(<id>)` is treated specially, so parentheses inside the summary are kept.
+pub fn parse_page_line(line: &str) -> Result<DnotePage, DnoteError> {
+    let parse_error = || DnoteError::Parse {
+        line: line.to_string(),
+    };
+    let cleaned = clean_line(line);
+    let (id, summary) = cleaned
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(')'))
+        .ok_or_else(parse_error)?;
+    let id = id.trim().parse().map_err(|_| parse_error())?;
+    let summary = summary
+        .trim()
+        .strip_suffix(MORE_MARKER)
+        .unwrap_or(summary)
+        .trim()
+        .to_string();
+    Ok(DnotePage {
+        id,
+        summary,
+        ..Default::default()
+    })
+}
+
+/// Parse the output of `dnote find <query>`, one `(<id>) (<book>) <snippet>` line per hit.
+pub fn parse_search_results(output: &str) -> Result<Vec<SearchHit>, DnoteError> {
+    output
+        .lines()
+        .map(clean_line)
+        .filter(|line| !line.is_empty() && !is_info_line(line))
+        .map(|line| parse_search_line(&line))
+        .collect()
+}
+
+/// Parse a single `(<id>) (<book>) <snippet>` search hit line.
+///
+/// Book names can't contain spaces, so the book ends at the first `) `.
+pub fn parse_search_line(line: &str) -> Result<SearchHit, DnoteError> {
+    let parse_error = || DnoteError::Parse {
+        line: line.to_string(),
+    };
+    let cleaned = clean_line(line);
+    let (id, rest) = cleaned
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(')'))
+        .ok_or_else(parse_error)?;
+    let id = id.trim().parse().map_err(|_| parse_error())?;
+    let rest = format!("{} ", rest.trim_start());
+    let (book_name, snippet) = rest
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(") "))
+        .ok_or_else(parse_error)?;
+    if book_name.is_empty() {
+        return Err(parse_error());
+    }
+    Ok(SearchHit {
+        book_name: book_name.to_string(),
+        id,
+        snippet: snippet.trim().to_string(),
+    })
+}
+
+/// Parse the output of `dnote view <id> --content-only`, which is the raw note body.
+pub fn parse_page_content(output: &str) -> DnotePageInfo {
+    DnotePageInfo {
+        content: output.trim().to_string(),
+    }
+}
+
+/// Parse the output of `dnote view <id>`: `key: value` info lines followed by the note body
+/// between two dashed separators.
+pub fn parse_page_info(output: &str) -> Result<DnotePage, DnoteError> {
+    let mut page = DnotePage::default();
+    let mut id = None;
+    let mut content_lines = Vec::new();
+    let mut in_content = false;
+    for raw in output.lines() {
+        let line = clean_line(raw);
+        if line.starts_with(CONTENT_SEPARATOR) {
+            in_content = !in_content;
+            continue;
+        }
+        if in_content {
+            content_lines.push(strip_ansi_escapes::strip_str(raw));
+            continue;
+        }
+        let Some((key, value)) = line
+            .strip_prefix(INFO_BULLET)
+            .and_then(|info| info.split_once(':'))
+        else {
+            continue;
+        };
+        let parse_error = || DnoteError::Parse {
+            line: raw.to_string(),
+        };
+        let value = value.trim();
+        match key.trim() {
+            "book name" => page.book_name = Some(value.to_string()),
+            "created at" => page.added_on = Some(parse_time(value).ok_or_else(parse_error)?),
+            "updated at" => page.edited_on = Some(parse_time(value).ok_or_else(parse_error)?),
+            "note id" => id = Some(value.parse().map_err(|_| parse_error())?),
+            "note uuid" => page.uuid = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    page.id = id.ok_or_else(|| DnoteError::Parse {
+        line: output.lines().next().unwrap_or_default().to_string(),
+    })?;
+    page.summary = summarize(&content_lines.join("\n"));
+    Ok(page)
+}
+
+/// Parse a dnote timestamp such as `Jan 2, 2006 3:04pm (MST)`, which is printed in local time.
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.split(" (").next().unwrap_or(value).trim();
+    let naive = NaiveDateTime::parse_from_str(value, TIME_FORMAT).ok()?;
+    let local = Local.from_local_datetime(&naive).earliest()?;
+    Some(local.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/dnote/",
+                $name
+            ))
+        };
+    }
+
+    fn page(id: u32, summary: &str) -> DnotePage {
+        DnotePage {
+            id,
+            summary: summary.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn local_time(value: &str) -> DateTime<Utc> {
+        let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap();
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn should_parse_book_names() {
+        let books = parse_books(fixture!("view_books_name_only.txt")).unwrap();
+        let names: Vec<_> = books.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "DevOpsPractices",
+                "DockerEssentials",
+                "MachineLearning",
+                "RustProgramming",
+                "notes-日本語",
+            ]
+        );
+        assert!(books.iter().all(|b| b.note_count.is_none()));
+    }
+
+    #[test]
+    fn should_parse_books_with_note_counts() {
+        let books = parse_books(fixture!("view_books.txt")).unwrap();
+        let counts: Vec<_> = books
+            .iter()
+            .map(|b| (b.name.as_str(), b.note_count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("DevOpsPractices", Some(3)),
+                ("DockerEssentials", Some(12)),
+                ("MachineLearning", Some(0)),
+                ("notes (draft)", Some(1)),
+                ("notes-日本語", Some(2)),
+            ]
+        );
+        assert!(parse_book_line("  • ").is_err());
+    }
+
+    #[test]
+    fn should_parse_note_list_with_parentheses_unicode_and_more_markers() {
+        let pages = parse_pages(fixture!("view_book.txt")).unwrap();
+        assert_eq!(
+            pages,
+            vec![
+                page(1, "Introduction to Rust: Rust is a systems programming language focused on safety and performance."),
+                page(2, "Ownership and Borrowing: Understanding ownership in Rust is key to managing memory safety without garbage collection."),
+                page(12, "fix foo() call in bar (see #42)"),
+                page(13, "# Crates and Modules"),
+                page(27, "Ünïcödé → ✓ 日本語のメモ"),
+                page(31, "(nested) parens (a (b) c)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_parse_empty_book() {
+        assert_eq!(
+            parse_pages(fixture!("view_book_empty.txt")).unwrap(),
+            vec![]
+        );
+        assert_eq!(parse_pages("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn should_parse_colored_output() {
+        let pages = parse_pages(fixture!("view_book_colored.txt")).unwrap();
+        assert_eq!(
+            pages,
+            vec![page(1, "Introduction to Rust"), page(12, "fix foo() call")]
+        );
+    }
+
+    #[test]
+    fn should_parse_content_only_output() {
+        let info = parse_page_content(fixture!("view_note_content_only.txt"));
+        assert!(info.content.starts_with("# Crates and Modules\n\n"));
+        assert!(info.content.ends_with("- `pub(crate)` limits visibility"));
+    }
+
+    #[test]
+    fn should_parse_note_info() {
+        let page = parse_page_info(fixture!("view_note_info.txt")).unwrap();
+        assert_eq!(
+            page,
+            DnotePage {
+                id: 13,
+                summary: "# Crates and Modules".into(),
+                uuid: Some("3f2a8c2e-5b7d-4c1e-9a0f-6d8e2b1c4a57".into()),
+                book_name: Some("RustProgramming".into()),
+                added_on: Some(local_time("2024-03-04 09:15")),
+                edited_on: Some(local_time("2024-10-01 23:02")),
+                public: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_reject_note_info_without_id_or_with_bad_time() {
+        assert!(parse_page_info("  • book name: rust\n").is_err());
+        let bad_time = "  • created at: yesterday\n  • note id: 1\n";
+        match parse_page_info(bad_time) {
+            Err(DnoteError::Parse { line }) => assert_eq!(line, "  • created at: yesterday"),
+            other => panic!("expected parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn should_reject_malformed_note_lines() {
+        for line in [
+            "no id here",
+            "(abc) summary",
+            "12) missing open paren",
+            "(12 missing close",
+        ] {
+            match parse_page_line(line) {
+                Err(DnoteError::Parse { line: offending }) => assert_eq!(offending, line),
+                other => panic!("expected parse error for {line:?}, got {other:?}"),
+            }
+        }
+        assert!(parse_pages("  • on book rust\n(1) ok\ngarbage\n").is_err());
+    }
+
+    #[test]
+    fn should_parse_search_results() {
+        let hit = |id, book_name: &str, snippet: &str| SearchHit {
+            book_name: book_name.into(),
+            id,
+            snippet: snippet.into(),
+        };
+        assert_eq!(
+            parse_search_results(fixture!("find.txt")).unwrap(),
+            vec![
+                hit(2, "RustProgramming", "Ownership and Borrowing: Understanding ownership in Rust is key to managing memory"),
+                hit(13, "RustProgramming", "...visibility rules and ownership of modules..."),
+                hit(41, "notes-日本語", "日本語のownershipメモ (draft)"),
+            ]
+        );
+        assert_eq!(parse_search_results("").unwrap(), vec![]);
+        assert_eq!(parse_search_line("(7) (go)").unwrap(), hit(7, "go", ""));
+        for line in ["(7) go snippet", "(x) (go) snippet", "(7) () snippet"] {
+            assert!(parse_search_line(line).is_err(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn should_parse_notes_without_summary() {
+        assert_eq!(parse_page_line("(5) [---More---]").unwrap(), page(5, ""));
+        assert_eq!(parse_page_line("(6)").unwrap(), page(6, ""));
+    }
+}
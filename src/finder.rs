@@ -0,0 +1,116 @@
+//! Fuzzy ranking of books and notes for the finder popup.
+
+use std::cmp::Reverse;
+
+use nucleo_matcher::{
+    pattern::{CaseMatching, Normalization, Pattern},
+    Config, Matcher, Utf32Str,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::dnote::NoteId;
+
+/// A book or a note that the finder can jump to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FinderItem {
+    Book(String),
+    Note {
+        book: String,
+        id: NoteId,
+        summary: String,
+    },
+}
+
+impl FinderItem {
+    pub fn book(&self) -> &str {
+        match self {
+            FinderItem::Book(book) | FinderItem::Note { book, .. } => book,
+        }
+    }
+
+    /// The text the query is matched against, the book name followed by the note summary.
+    pub fn text(&self) -> String {
+        match self {
+            FinderItem::Book(book) => book.clone(),
+            FinderItem::Note { book, summary, .. } => format!("{book} {summary}"),
+        }
+    }
+}
+
+/// An item that matched a query.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct FinderMatch {
+    /// Index of the item in the items that were ranked.
+    pub index: usize,
+    pub score: u32,
+    /// Indices of the matched chars in the item's [`FinderItem::text`], sorted.
+    pub indices: Vec<u32>,
+}
+
+/// Rank `items` against `query`, best match first. Words of the query are matched separately
+/// and fzf's syntax is supported, e.g. `^rust` or `'exact`. An empty query keeps every item, in
+/// order.
+pub fn rank(items: &[FinderItem], query: &str) -> Vec<FinderMatch> {
+    let pattern = Pattern::parse(query, CaseMatching::Smart, Normalization::Smart);
+    let mut matcher = Matcher::new(Config::DEFAULT);
+    let mut buf = Vec::new();
+    let mut matches: Vec<FinderMatch> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let text = item.text();
+            let mut indices = Vec::new();
+            let score =
+                pattern.indices(Utf32Str::new(&text, &mut buf), &mut matcher, &mut indices)?;
+            indices.sort_unstable();
+            indices.dedup();
+            Some(FinderMatch {
+                index,
+                score,
+                indices,
+            })
+        })
+        .collect();
+    // The sort is stable, so equally good matches stay in book order
+    matches.sort_by_key(|m| Reverse(m.score));
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn note(book: &str, id: NoteId, summary: &str) -> FinderItem {
+        FinderItem::Note {
+            book: book.into(),
+            id,
+            summary: summary.into(),
+        }
+    }
+
+    #[test]
+    fn rank_orders_best_matches_first() {
+        let items = vec![
+            FinderItem::Book("docker".into()),
+            note("docker", 1, "compose up"),
+            FinderItem::Book("rust".into()),
+            note("rust", 2, "# Ownership"),
+            note("rust", 3, "cargo clippy"),
+        ];
+        let ranked =
+            |query| -> Vec<usize> { rank(&items, query).iter().map(|m| m.index).collect() };
+        assert_eq!(ranked(""), vec![0, 1, 2, 3, 4]);
+        assert_eq!(ranked("clippy"), vec![4]);
+        assert_eq!(ranked("rust own"), vec![3]);
+        assert_eq!(ranked("zzz"), Vec::<usize>::new());
+        assert_eq!(ranked("rust")[0], 2);
+    }
+
+    #[test]
+    fn rank_reports_matched_chars() {
+        let items = vec![note("rust", 2, "cargo clippy")];
+        assert_eq!(rank(&items, "cgo")[0].indices, vec![5, 8, 9]);
+    }
+}
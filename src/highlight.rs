@@ -0,0 +1,100 @@
+//! Syntax highlighting of code blocks with syntect's bundled grammars and themes.
+
+use lazy_static::lazy_static;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{self, FontStyle, ThemeSet},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+/// Theme used when the config doesn't pick one.
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+lazy_static! {
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEMES: ThemeSet = ThemeSet::load_defaults();
+}
+
+/// Names of the bundled themes, for the `syntax_theme` config key.
+pub fn theme_names() -> Vec<&'static str> {
+    THEMES.themes.keys().map(String::as_str).collect()
+}
+
+/// Highlight `code` written in `lang`, a language name or file extension like `rust` or `sh`.
+/// Returns `None` when the language or `theme` is unknown, so the caller can fall back to
+/// plain text.
+pub fn highlight(code: &str, lang: &str, theme: &str) -> Option<Vec<Line<'static>>> {
+    if lang.is_empty() {
+        return None;
+    }
+    let syntax = SYNTAXES.find_syntax_by_token(lang)?;
+    let Some(theme) = THEMES.themes.get(theme) else {
+        log::warn!(
+            "Unknown syntax theme {theme:?}, expected one of {:?}",
+            theme_names()
+        );
+        return None;
+    };
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let ranges = highlighter.highlight_line(line, &SYNTAXES).ok()?;
+        let spans: Vec<Span> = ranges
+            .into_iter()
+            .map(|(style, text)| {
+                let text = text.trim_end_matches(['\n', '\r']).to_string();
+                Span::styled(text, to_style(style))
+            })
+            .filter(|span| !span.content.is_empty())
+            .collect();
+        lines.push(Line::from(spans));
+    }
+    Some(lines)
+}
+
+/// Convert a syntect style, keeping the terminal's background.
+fn to_style(style: highlighting::Style) -> Style {
+    let fg = style.foreground;
+    let mut modifiers = Modifier::empty();
+    if style.font_style.contains(FontStyle::BOLD) {
+        modifiers |= Modifier::BOLD;
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        modifiers |= Modifier::ITALIC;
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        modifiers |= Modifier::UNDERLINED;
+    }
+    Style::default()
+        .fg(Color::Rgb(fg.r, fg.g, fg.b))
+        .add_modifier(modifiers)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn highlight_colors_known_languages() {
+        let lines = highlight("fn main() {}\nlet x = 1;\n", "rust", DEFAULT_THEME).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].to_string(), "fn main() {}");
+        assert!(lines[0].spans.len() > 1);
+        assert!(lines[0].spans.iter().all(|span| span.style.fg.is_some()));
+        assert!(highlight("ls -la", "sh", DEFAULT_THEME).is_some());
+    }
+
+    #[test]
+    fn highlight_falls_back_on_unknown_language_or_theme() {
+        assert_eq!(highlight("x", "no-such-lang", DEFAULT_THEME), None);
+        assert_eq!(highlight("x", "", DEFAULT_THEME), None);
+        assert_eq!(highlight("x", "rust", "no-such-theme"), None);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod action;
+pub mod app;
+pub mod cli;
+pub mod components;
+pub mod config;
+pub mod dnote;
+pub mod errors;
+pub mod finder;
+pub mod highlight;
+pub mod logging;
+pub mod markdown;
+pub mod search;
+pub mod sort;
+pub mod state;
+pub mod store;
+pub mod trash;
+pub mod tui;
@@ -1,27 +1,19 @@
-pub mod action;
-pub mod app;
-pub mod cli;
-pub mod components;
-pub mod config;
-pub mod dnote;
-pub mod errors;
-pub mod logging;
-pub mod state;
-pub mod tui;
-
 use clap::Parser;
-use cli::Cli;
 use color_eyre::eyre::Result;
-
-use crate::app::App;
+use dnote_tui::{app::App, cli::Cli, config::Config, errors, logging, store};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    crate::errors::init()?;
-    crate::logging::init()?;
+    errors::init()?;
+    logging::init()?;
 
     let args = Cli::parse();
-    let mut app = App::new(args.tick_rate, args.frame_rate)?;
+    let mut config = Config::new()?;
+    if let Some(dnote_bin) = args.dnote_bin {
+        config.config.dnote.bin = dnote_bin;
+    }
+    let store = store::open(&config.config)?;
+    let mut app = App::new(args.tick_rate, args.frame_rate, config, store)?;
     app.run().await?;
     Ok(())
 }
@@ -0,0 +1,610 @@
+//! Rendering of markdown notes to styled ratatui [`Text`].
+
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::{
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+};
+
+use crate::{config::Config, highlight, state::Mode};
+
+/// Styles of the markdown elements, read from the `Content` section of the config's `styles`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkdownStyles {
+    pub headings: [Style; 6],
+    pub emphasis: Style,
+    pub strong: Style,
+    pub strikethrough: Style,
+    pub code: Style,
+    pub code_block: Style,
+    pub blockquote: Style,
+    pub list_marker: Style,
+    pub link: Style,
+    pub link_url: Style,
+    pub rule: Style,
+    pub table_header: Style,
+    pub table_border: Style,
+    /// Theme highlighting fenced code blocks, from the config's `syntax_theme`.
+    pub syntax_theme: String,
+}
+
+impl Default for MarkdownStyles {
+    fn default() -> Self {
+        Self {
+            headings: [
+                Style::new().cyan().bold().underlined(),
+                Style::new().cyan().bold(),
+                Style::new().blue().bold(),
+                Style::new().blue(),
+                Style::new().blue(),
+                Style::new().blue(),
+            ],
+            emphasis: Style::new().italic(),
+            strong: Style::new().bold(),
+            strikethrough: Style::new().crossed_out(),
+            code: Style::new().yellow(),
+            code_block: Style::new().yellow(),
+            blockquote: Style::new().dark_gray(),
+            list_marker: Style::new().green(),
+            link: Style::new().blue().underlined(),
+            link_url: Style::new().dark_gray(),
+            rule: Style::new().dark_gray(),
+            table_header: Style::new().bold(),
+            table_border: Style::new().dark_gray(),
+            syntax_theme: highlight::DEFAULT_THEME.to_string(),
+        }
+    }
+}
+
+impl MarkdownStyles {
+    /// Read the styles from the config, keeping the default for every style it doesn't set.
+    pub fn from_config(config: &Config) -> Self {
+        let mut md = Self {
+            syntax_theme: config.config.syntax_theme.clone(),
+            ..Self::default()
+        };
+        let Some(styles) = config.styles.get(&Mode::Content) else {
+            return md;
+        };
+        let set = |style: &mut Style, key: &str| {
+            if let Some(configured) = styles.get(key) {
+                *style = *configured;
+            }
+        };
+        for (level, style) in md.headings.iter_mut().enumerate() {
+            set(style, &format!("heading{}", level + 1));
+        }
+        set(&mut md.emphasis, "emphasis");
+        set(&mut md.strong, "strong");
+        set(&mut md.strikethrough, "strikethrough");
+        set(&mut md.code, "code");
+        set(&mut md.code_block, "code_block");
+        set(&mut md.blockquote, "blockquote");
+        set(&mut md.list_marker, "list_marker");
+        set(&mut md.link, "link");
+        set(&mut md.link_url, "link_url");
+        set(&mut md.rule, "rule");
+        set(&mut md.table_header, "table_header");
+        set(&mut md.table_border, "table_border");
+        md
+    }
+}
+
+/// Render the markdown `content` for a pane `width` columns wide.
+pub fn render(content: &str, styles: &MarkdownStyles, width: u16) -> Text<'static> {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut renderer = Renderer::new(styles, width);
+    for event in Parser::new_ext(content, options) {
+        renderer.event(event);
+    }
+    renderer.flush_line();
+    Text::from(renderer.lines)
+}
+
+/// A list being rendered, with the number of its next item if it is ordered.
+struct List {
+    next: Option<u64>,
+    /// Width of the item markers, continuation lines are indented by it.
+    indent: usize,
+}
+
+#[derive(Default)]
+struct Table {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Vec<Span<'static>>>>,
+    /// Whether the first row is the header.
+    has_head: bool,
+}
+
+struct Renderer<'a> {
+    styles: &'a MarkdownStyles,
+    width: u16,
+    lines: Vec<Line<'static>>,
+    line: Vec<Span<'static>>,
+    /// Inline styles in effect, innermost last.
+    inline: Vec<Style>,
+    quote_depth: usize,
+    lists: Vec<List>,
+    /// Marker of a list item, put at the start of its first line.
+    marker: Option<String>,
+    /// Destination and text of the link being rendered.
+    link: Option<(String, String)>,
+    /// Language and content of the fenced code block being rendered.
+    code_block: Option<(String, String)>,
+    table: Option<Table>,
+    /// Set at the end of a block, so the next one is separated by a blank line.
+    needs_blank: bool,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(styles: &'a MarkdownStyles, width: u16) -> Self {
+        Self {
+            styles,
+            width,
+            lines: Vec::new(),
+            line: Vec::new(),
+            inline: Vec::new(),
+            quote_depth: 0,
+            lists: Vec::new(),
+            marker: None,
+            link: None,
+            code_block: None,
+            table: None,
+            needs_blank: false,
+        }
+    }
+
+    fn event(&mut self, event: Event<'_>) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.text(&text),
+            Event::Code(code) => {
+                let style = self.inline_style().patch(self.styles.code);
+                self.push(Span::styled(code.into_string(), style));
+            }
+            Event::Html(html) | Event::InlineHtml(html) => self.text(&html),
+            // Notes are mostly written line by line, so keep their line breaks
+            Event::SoftBreak | Event::HardBreak => self.flush_line(),
+            Event::Rule => {
+                self.block_start();
+                let width = (self.width as usize)
+                    .saturating_sub(self.prefix_width())
+                    .max(3);
+                self.push(Span::styled("─".repeat(width), self.styles.rule));
+                self.block_end();
+            }
+            Event::TaskListMarker(checked) => {
+                let marker = if checked { "[x] " } else { "[ ] " };
+                self.push(Span::styled(marker, self.styles.list_marker));
+            }
+            Event::FootnoteReference(name) => self.text(&format!("[^{name}]")),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Paragraph | Tag::HtmlBlock => self.block_start(),
+            Tag::Heading { level, .. } => {
+                self.block_start();
+                self.inline.push(self.styles.headings[heading_index(level)]);
+            }
+            Tag::BlockQuote(_) => {
+                self.block_start();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(kind) => {
+                self.block_start();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code_block = Some((lang, String::new()));
+            }
+            Tag::List(start) => {
+                match self.lists.is_empty() {
+                    true => self.block_start(),
+                    false => self.flush_line(),
+                }
+                self.lists.push(List {
+                    next: start,
+                    indent: 2,
+                });
+            }
+            Tag::Item => {
+                self.flush_line();
+                if std::mem::take(&mut self.needs_blank) {
+                    self.blank_line();
+                }
+                if let Some(list) = self.lists.last_mut() {
+                    let marker = match &mut list.next {
+                        Some(n) => {
+                            *n += 1;
+                            format!("{}. ", *n - 1)
+                        }
+                        None => "• ".to_string(),
+                    };
+                    list.indent = marker.chars().count();
+                    self.marker = Some(marker);
+                }
+            }
+            Tag::Table(alignments) => {
+                self.block_start();
+                self.table = Some(Table {
+                    alignments,
+                    ..Default::default()
+                });
+            }
+            Tag::TableHead => {
+                if let Some(table) = &mut self.table {
+                    table.has_head = true;
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::TableCell => {
+                if let Some(row) = self.table.as_mut().and_then(|t| t.rows.last_mut()) {
+                    row.push(Vec::new());
+                }
+            }
+            Tag::Emphasis => self.inline.push(self.styles.emphasis),
+            Tag::Strong => self.inline.push(self.styles.strong),
+            Tag::Strikethrough => self.inline.push(self.styles.strikethrough),
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                self.inline.push(self.styles.link);
+                self.link = Some((dest_url.into_string(), String::new()));
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::HtmlBlock => self.block_end(),
+            TagEnd::Heading(_) => {
+                self.inline.pop();
+                self.block_end();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush_line();
+                self.quote_depth -= 1;
+                self.needs_blank = true;
+            }
+            TagEnd::CodeBlock => {
+                if let Some((lang, code)) = self.code_block.take() {
+                    for line in self.code_lines(&lang, &code) {
+                        self.push_line(line);
+                    }
+                }
+                self.block_end();
+            }
+            TagEnd::List(_) => {
+                self.flush_line();
+                self.lists.pop();
+                self.needs_blank = self.lists.is_empty();
+            }
+            TagEnd::Item => {
+                self.flush_line();
+                self.marker = None;
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    for line in self.table_lines(table) {
+                        self.push_line(line);
+                    }
+                }
+                self.block_end();
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.inline.pop();
+            }
+            TagEnd::Link | TagEnd::Image => {
+                self.inline.pop();
+                if let Some((url, text)) = self.link.take() {
+                    if !url.is_empty() && url != text {
+                        self.push(Span::styled(format!(" ({url})"), self.styles.link_url));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if let Some((_, code)) = &mut self.code_block {
+            code.push_str(text);
+            return;
+        }
+        if let Some((_, link_text)) = &mut self.link {
+            link_text.push_str(text);
+        }
+        let style = self.inline_style();
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.flush_line();
+            }
+            if !line.is_empty() {
+                self.push(Span::styled(line.to_string(), style));
+            }
+        }
+    }
+
+    /// Lines of a fenced code block in language `lang`, highlighted if the language is known.
+    fn code_lines(&self, lang: &str, code: &str) -> Vec<Line<'static>> {
+        let code = code.trim_end_matches('\n');
+        match highlight::highlight(code, lang, &self.styles.syntax_theme) {
+            Some(lines) => lines
+                .into_iter()
+                .map(|line| {
+                    let mut spans = vec![Span::raw("  ")];
+                    spans.extend(line.spans);
+                    Line::from(spans)
+                })
+                .collect(),
+            None => code
+                .split('\n')
+                .map(|line| Line::styled(format!("  {line}"), self.styles.code_block))
+                .collect(),
+        }
+    }
+
+    fn table_lines(&self, table: Table) -> Vec<Line<'static>> {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut widths = vec![0; columns];
+        for row in &table.rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell.iter().map(Span::width).sum());
+            }
+        }
+        let border = self.styles.table_border;
+        let rule = |left: &str, mid: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+            Line::styled(format!("{left}{}{right}", segments.join(mid)), border)
+        };
+
+        let mut lines = vec![rule("┌", "┬", "┐")];
+        for (r, row) in table.rows.into_iter().enumerate() {
+            let header = table.has_head && r == 0;
+            let mut spans = vec![Span::styled("│", border)];
+            for (i, width) in widths.iter().enumerate() {
+                let cell = row.get(i).cloned().unwrap_or_default();
+                let pad = width - cell.iter().map(Span::width).sum::<usize>();
+                let (before, after) = match table.alignments.get(i) {
+                    Some(Alignment::Right) => (pad, 0),
+                    Some(Alignment::Center) => (pad / 2, pad - pad / 2),
+                    _ => (0, pad),
+                };
+                spans.push(Span::raw(" ".repeat(before + 1)));
+                spans.extend(cell.into_iter().map(|span| match header {
+                    true => span.patch_style(self.styles.table_header),
+                    false => span,
+                }));
+                spans.push(Span::raw(" ".repeat(after + 1)));
+                spans.push(Span::styled("│", border));
+            }
+            lines.push(Line::from(spans));
+            if header {
+                lines.push(rule("├", "┼", "┤"));
+            }
+        }
+        lines.push(rule("└", "┴", "┘"));
+        lines
+    }
+
+    fn inline_style(&self) -> Style {
+        self.inline
+            .iter()
+            .fold(Style::default(), |style, inline| style.patch(*inline))
+    }
+
+    /// Push a span to the current line, or to the current table cell.
+    fn push(&mut self, span: Span<'static>) {
+        if let Some(cell) = self
+            .table
+            .as_mut()
+            .and_then(|t| t.rows.last_mut())
+            .and_then(|row| row.last_mut())
+        {
+            cell.push(span);
+            return;
+        }
+        if self.line.is_empty() {
+            self.line = self.prefix();
+        }
+        self.line.push(span);
+    }
+
+    /// Push a complete line, prefixed like the current line would be.
+    fn push_line(&mut self, line: Line<'static>) {
+        self.flush_line();
+        let mut spans = self.prefix();
+        spans.extend(line.spans);
+        self.lines.push(Line::from(spans).style(line.style));
+    }
+
+    fn flush_line(&mut self) {
+        if !self.line.is_empty() {
+            let line = std::mem::take(&mut self.line);
+            self.lines.push(Line::from(line));
+        }
+    }
+
+    fn blank_line(&mut self) {
+        let quotes = self.quote_prefix();
+        self.lines.push(Line::from(quotes));
+    }
+
+    fn block_start(&mut self) {
+        self.flush_line();
+        if std::mem::take(&mut self.needs_blank) {
+            self.blank_line();
+        }
+    }
+
+    fn block_end(&mut self) {
+        self.flush_line();
+        self.needs_blank = true;
+    }
+
+    fn quote_prefix(&self) -> Vec<Span<'static>> {
+        (0..self.quote_depth)
+            .map(|_| Span::styled("│ ", self.styles.blockquote))
+            .collect()
+    }
+
+    /// The quote bars and list indentation starting a line, with the marker of a new list item.
+    fn prefix(&mut self) -> Vec<Span<'static>> {
+        let mut spans = self.quote_prefix();
+        let outer: usize = self
+            .lists
+            .iter()
+            .rev()
+            .skip(1)
+            .map(|list| list.indent)
+            .sum();
+        if outer > 0 {
+            spans.push(Span::raw(" ".repeat(outer)));
+        }
+        match (self.marker.take(), self.lists.last()) {
+            (Some(marker), _) => spans.push(Span::styled(marker, self.styles.list_marker)),
+            (None, Some(list)) => spans.push(Span::raw(" ".repeat(list.indent))),
+            (None, None) => {}
+        }
+        if self.quote_depth > 0 {
+            let style = self.styles.blockquote;
+            return spans
+                .into_iter()
+                .map(|span| span.patch_style(style))
+                .collect();
+        }
+        spans
+    }
+
+    fn prefix_width(&self) -> usize {
+        2 * self.quote_depth + self.lists.iter().map(|list| list.indent).sum::<usize>()
+    }
+}
+
+fn heading_index(level: HeadingLevel) -> usize {
+    match level {
+        HeadingLevel::H1 => 0,
+        HeadingLevel::H2 => 1,
+        HeadingLevel::H3 => 2,
+        HeadingLevel::H4 => 3,
+        HeadingLevel::H5 => 4,
+        HeadingLevel::H6 => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use ratatui::style::Color;
+
+    use super::*;
+    use crate::config::Styles;
+
+    fn config_with_styles(pairs: &[(&str, Style)]) -> Config {
+        let styles: HashMap<String, Style> = pairs
+            .iter()
+            .map(|(key, style)| (key.to_string(), *style))
+            .collect();
+        Config {
+            styles: Styles(HashMap::from([(Mode::Content, styles)])),
+            ..Default::default()
+        }
+    }
+
+    fn plain(text: &Text) -> Vec<String> {
+        text.lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn render_lays_out_blocks() {
+        let md = "# Title\n\nSome *text* with `code`\nand a [link](https://x.dev).\n\n\
+                  > quoted\n\n- one\n- two\n  1. nested\n\n---\n\n```sh\nls -la\n```";
+        let text = render(md, &MarkdownStyles::default(), 20);
+        assert_eq!(
+            plain(&text),
+            vec![
+                "Title",
+                "",
+                "Some text with code",
+                "and a link (https://x.dev).",
+                "",
+                "│ quoted",
+                "",
+                "• one",
+                "• two",
+                "  1. nested",
+                "",
+                "────────────────────",
+                "",
+                "  ls -la",
+            ]
+        );
+    }
+
+    #[test]
+    fn render_styles_inline_elements() {
+        let styles = MarkdownStyles::default();
+        let text = render("## Intro\n\n**bold** and `x`", &styles, 20);
+        assert_eq!(text.lines[0].spans[0].style, styles.headings[1]);
+        assert_eq!(text.lines[2].spans[0].style, styles.strong);
+        assert_eq!(text.lines[2].spans[2].style, styles.code);
+    }
+
+    #[test]
+    fn render_aligns_tables() {
+        let md = "| Cmd | Use |\n|-----|----:|\n| ls | list |\n| cd | go |";
+        let text = render(md, &MarkdownStyles::default(), 40);
+        assert_eq!(
+            plain(&text),
+            vec![
+                "┌─────┬──────┐",
+                "│ Cmd │  Use │",
+                "├─────┼──────┤",
+                "│ ls  │ list │",
+                "│ cd  │   go │",
+                "└─────┴──────┘",
+            ]
+        );
+    }
+
+    #[test]
+    fn styles_are_read_from_the_content_section() {
+        let configured = Style::new().red();
+        let mut config = config_with_styles(&[("heading2", configured), ("code", configured)]);
+        config.config.syntax_theme = "InspiredGitHub".into();
+        let styles = MarkdownStyles::from_config(&config);
+        assert_eq!(styles.headings[1], configured);
+        assert_eq!(styles.code, configured);
+        assert_eq!(styles.strong, MarkdownStyles::default().strong);
+        assert_eq!(styles.syntax_theme, "InspiredGitHub");
+    }
+
+    #[test]
+    fn render_highlights_known_code_languages() {
+        let styles = MarkdownStyles::default();
+        let text = render("```rust\nlet x = 1;\n```", &styles, 20);
+        assert_eq!(plain(&text), vec!["  let x = 1;"]);
+        assert!(text.lines[0].spans.len() > 2);
+        assert!(matches!(
+            text.lines[0].spans[1].style.fg,
+            Some(Color::Rgb(..))
+        ));
+
+        let text = render("```nope\nlet x = 1;\n```", &styles, 20);
+        assert_eq!(text.lines[0].style, styles.code_block);
+    }
+}
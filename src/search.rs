@@ -0,0 +1,106 @@
+//! Full-text search over note contents, shared by the store backends and the search popup.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dnote::NoteId;
+
+/// Most hits a search returns, so that a one-letter query can't flood the results list.
+pub const MAX_RESULTS: usize = 200;
+
+/// Characters of context kept on each side of the match in a snippet.
+const SNIPPET_CONTEXT: usize = 30;
+
+/// A note whose content matches a search query.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub book_name: String,
+    pub id: NoteId,
+    /// The matching part of the note, on a single line.
+    pub snippet: String,
+}
+
+/// Byte ranges of the case-insensitive occurrences of `query` in `text`.
+pub fn match_ranges(text: &str, query: &str) -> Vec<Range<usize>> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut ranges = Vec::new();
+    let mut search_from = 0;
+    for (start, _) in text.char_indices() {
+        if start < search_from {
+            continue;
+        }
+        let mut rest = text[start..].char_indices();
+        let mut lowered = Vec::new();
+        let mut end = start;
+        while lowered.len() < query.len() {
+            let Some((offset, c)) = rest.next() else {
+                break;
+            };
+            lowered.extend(c.to_lowercase());
+            end = start + offset + c.len_utf8();
+        }
+        if lowered == query {
+            ranges.push(start..end);
+            search_from = end;
+        }
+    }
+    ranges
+}
+
+/// A one-line snippet of `content` around the first occurrence of `query`, or `None` if the
+/// content doesn't contain it.
+pub fn snippet(content: &str, query: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let found = match_ranges(line, query.trim()).into_iter().next()?;
+        let line = line.trim_end();
+        let start = line[..found.start]
+            .char_indices()
+            .rev()
+            .nth(SNIPPET_CONTEXT - 1)
+            .map(|(i, _)| i);
+        let end = line[found.end..]
+            .char_indices()
+            .nth(SNIPPET_CONTEXT)
+            .map(|(i, _)| found.end + i);
+        let mut snippet = String::new();
+        if start.is_some() {
+            snippet.push('…');
+        }
+        snippet.push_str(line[start.unwrap_or(0)..end.unwrap_or(line.len())].trim());
+        if end.is_some() {
+            snippet.push('…');
+        }
+        Some(snippet)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn match_ranges_ignore_case() {
+        let text = "Cargo builds, cargo tests";
+        assert_eq!(match_ranges(text, "CARGO"), vec![0..5, 14..19]);
+        assert!(match_ranges(text, "").is_empty());
+        assert_eq!(match_ranges("Ünïcödé ünïcödé", "ünï"), vec![0..5, 12..17]);
+        assert_eq!(match_ranges("aaa", "aa"), vec![0..2]);
+    }
+
+    #[test]
+    fn snippet_crops_the_matching_line() {
+        let content = "# Title\n\nsome intro\nThe borrow checker enforces ownership rules at compile time, which prevents data races entirely.";
+        assert_eq!(
+            snippet(content, "ownership").as_deref(),
+            Some("The borrow checker enforces ownership rules at compile time, which…")
+        );
+        assert_eq!(snippet(content, "title").as_deref(), Some("# Title"));
+        assert_eq!(snippet(content, "missing"), None);
+    }
+}
@@ -0,0 +1,196 @@
+//! Sort orders for the pages list, remembered per book across sessions.
+
+use std::{cmp::Ordering, collections::HashMap, fs, io::ErrorKind, path::PathBuf};
+
+use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dnote::{DnotePage, NoteId},
+    store::NoteStore,
+};
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SortKey {
+    #[default]
+    Id,
+    Summary,
+    Added,
+    Edited,
+    Length,
+}
+
+impl SortKey {
+    const ALL: [SortKey; 5] = [
+        SortKey::Id,
+        SortKey::Summary,
+        SortKey::Added,
+        SortKey::Edited,
+        SortKey::Length,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SortKey::Id => "id",
+            SortKey::Summary => "summary",
+            SortKey::Added => "added",
+            SortKey::Edited => "edited",
+            SortKey::Length => "length",
+        }
+    }
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PageSort {
+    pub key: SortKey,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl PageSort {
+    /// The next key in the cycle, ascending.
+    pub fn next_key(self) -> Self {
+        let index = SortKey::ALL
+            .iter()
+            .position(|k| *k == self.key)
+            .unwrap_or(0);
+        Self {
+            key: SortKey::ALL[(index + 1) % SortKey::ALL.len()],
+            descending: false,
+        }
+    }
+
+    pub fn reversed(self) -> Self {
+        Self {
+            descending: !self.descending,
+            ..self
+        }
+    }
+
+    /// Short description for the pane title, e.g. "edited ↓".
+    pub fn label(&self) -> String {
+        let arrow = match self.descending {
+            true => "↓",
+            false => "↑",
+        };
+        format!("{} {arrow}", self.key.label())
+    }
+
+    /// Sort `pages`, reading their content from `store` when sorting by length. Ties are broken
+    /// by id, so the order is stable across reloads.
+    pub fn sort(&self, store: &dyn NoteStore, pages: &mut [DnotePage]) -> Result<()> {
+        let mut lengths: HashMap<NoteId, usize> = HashMap::new();
+        if self.key == SortKey::Length {
+            for page in pages.iter() {
+                let content = store.get_page_content(page.id)?.content;
+                lengths.insert(page.id, content.chars().count());
+            }
+        }
+        pages.sort_by(|a, b| {
+            let ordering = match self.key {
+                SortKey::Id => Ordering::Equal,
+                SortKey::Summary => a.summary.to_lowercase().cmp(&b.summary.to_lowercase()),
+                SortKey::Added => a.added_on.cmp(&b.added_on),
+                // Notes never edited count as edited when they were added, as the list shows
+                SortKey::Edited => a.edited_on.or(a.added_on).cmp(&b.edited_on.or(b.added_on)),
+                SortKey::Length => lengths.get(&a.id).cmp(&lengths.get(&b.id)),
+            }
+            .then(a.id.cmp(&b.id));
+            match self.descending {
+                true => ordering.reverse(),
+                false => ordering,
+            }
+        });
+        Ok(())
+    }
+}
+
+/// The sort chosen for each book, saved to a JSON file, usually `page_sorts.json` in the app's
+/// data dir.
+#[derive(Debug, Clone, Default)]
+pub struct PageSorts {
+    path: PathBuf,
+    sorts: HashMap<String, PageSort>,
+}
+
+impl PageSorts {
+    /// Load the sorts saved at `path`, starting afresh if there are none or they are unreadable.
+    pub fn load(path: PathBuf) -> Self {
+        let sorts = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|err| {
+                log::warn!("Ignoring unreadable page sorts {}: {err}", path.display());
+                HashMap::new()
+            }),
+            Err(err) if err.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(err) => {
+                log::warn!("Failed to read page sorts {}: {err}", path.display());
+                HashMap::new()
+            }
+        };
+        Self { path, sorts }
+    }
+
+    pub fn get(&self, book: &str) -> PageSort {
+        self.sorts.get(book).copied().unwrap_or_default()
+    }
+
+    /// Remember `sort` for `book` and save all sorts.
+    pub fn set(&mut self, book: &str, sort: PageSort) -> Result<()> {
+        self.sorts.insert(book.to_string(), sort);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&self.sorts)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::store::memory::MemoryStore;
+
+    fn sorted_ids(store: &MemoryStore, sort: PageSort) -> Vec<NoteId> {
+        let mut pages = store.get_pages("rust").unwrap();
+        sort.sort(store, &mut pages).unwrap();
+        pages.iter().map(|page| page.id).collect()
+    }
+
+    #[test]
+    fn page_sort_orders_by_each_key() {
+        let store = MemoryStore::with_notes([
+            ("rust", "cargo doc --open"),
+            ("rust", "Borrowing"),
+            ("rust", "cargo"),
+        ])
+        .unwrap();
+        let sort = PageSort::default();
+        assert_eq!(sorted_ids(&store, sort), vec![1, 2, 3]);
+        assert_eq!(sorted_ids(&store, sort.reversed()), vec![3, 2, 1]);
+        let sort = sort.next_key();
+        assert_eq!(sort.label(), "summary ↑");
+        assert_eq!(sorted_ids(&store, sort), vec![2, 3, 1]);
+        let sort = PageSort {
+            key: SortKey::Length,
+            descending: true,
+        };
+        assert_eq!(sorted_ids(&store, sort), vec![1, 2, 3]);
+        assert_eq!(sort.next_key(), PageSort::default());
+    }
+
+    #[test]
+    fn page_sorts_are_saved_per_book() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("data").join("page_sorts.json");
+        let mut sorts = PageSorts::load(path.clone());
+        assert_eq!(sorts.get("rust"), PageSort::default());
+        let sort = PageSort::default().next_key().reversed();
+        sorts.set("rust", sort)?;
+        let sorts = PageSorts::load(path);
+        assert_eq!(sorts.get("rust"), sort);
+        assert_eq!(sorts.get("docker"), PageSort::default());
+        Ok(())
+    }
+}
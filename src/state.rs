@@ -1,9 +1,13 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+};
 
 use ratatui::widgets::ListState;
 use serde::{Deserialize, Serialize};
 
-use crate::dnote::{DnoteBook, DnotePage};
+use crate::dnote::{DnoteBook, DnotePage, NoteId};
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum InputMode {
@@ -18,12 +22,57 @@ pub enum Mode {
     Book,
     Page,
     Content,
+    Search,
+}
+
+/// A filter narrowing a [`StatefulList`] to the items matching a query.
+#[derive(Debug, Clone)]
+pub struct ListFilter<T> {
+    pub query: String,
+    /// Every item, the list only holds those matching the query.
+    all: Vec<T>,
+    /// Selection in `all` when the filter started, restored when it is cleared.
+    selected: Option<usize>,
+    /// Whether an item matches the query, which is lowercased.
+    matches: fn(&T, &str) -> bool,
+}
+
+// Function pointers can't be compared meaningfully, filters are told apart by what they hold
+impl<T: PartialEq> PartialEq for ListFilter<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.query, &self.all, self.selected) == (&other.query, &other.all, other.selected)
+    }
+}
+
+impl<T: Eq> Eq for ListFilter<T> {}
+
+impl<T: Hash> Hash for ListFilter<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (&self.query, &self.all, self.selected).hash(state);
+    }
+}
+
+impl<T: Clone> ListFilter<T> {
+    fn matching(&self) -> Vec<T> {
+        let query = self.query.to_lowercase();
+        self.all
+            .iter()
+            .filter(|item| (self.matches)(item, &query))
+            .cloned()
+            .collect()
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct StatefulList<T> {
     pub state: ListState,
     pub items: Vec<T>,
+    /// Indexes of the items marked for bulk operations.
+    pub marked: BTreeSet<usize>,
+    /// Where the visual range started, it runs from here to the selected item.
+    pub visual_anchor: Option<usize>,
+    /// Filter narrowing the items, if any.
+    pub filter: Option<ListFilter<T>>,
 }
 
 impl<T> StatefulList<T> {
@@ -31,6 +80,9 @@ pub fn with_items(items: Vec<T>) -> StatefulList<T> {
         StatefulList {
             state: ListState::default(),
             items,
+            marked: BTreeSet::new(),
+            visual_anchor: None,
+            filter: None,
         }
     }
 
@@ -70,9 +122,174 @@ pub fn previous(&mut self) {
         self.state.select(Some(i));
     }
 
+    /// Select the next item, wrapping around to the first, e.g. to step through filter matches.
+    pub fn next_wrapping(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = self
+            .state
+            .selected()
+            .map_or(0, |i| (i + 1) % self.items.len());
+        self.state.select(Some(i));
+    }
+
+    /// Select the previous item, wrapping around to the last.
+    pub fn previous_wrapping(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        let len = self.items.len();
+        let i = self.state.selected().map_or(0, |i| (i + len - 1) % len);
+        self.state.select(Some(i));
+    }
+
     pub fn unselect(&mut self) {
         self.state.select(None);
     }
+
+    pub fn filter_query(&self) -> Option<&str> {
+        self.filter.as_ref().map(|filter| filter.query.as_str())
+    }
+
+    /// Number of items, counting those hidden by the filter.
+    pub fn total_len(&self) -> usize {
+        self.all_items().len()
+    }
+
+    /// Every item, including those hidden by the filter.
+    pub fn all_items(&self) -> &[T] {
+        self.filter
+            .as_ref()
+            .map_or(&self.items, |filter| &filter.all)
+    }
+
+    /// Mark the selected item, or unmark it if it is marked.
+    pub fn toggle_mark(&mut self) {
+        if let Some(i) = self.state.selected() {
+            if !self.marked.remove(&i) {
+                self.marked.insert(i);
+            }
+        }
+    }
+
+    /// Start a visual range at the selected item, or mark the range if one was started.
+    pub fn toggle_visual(&mut self) {
+        match self.visual_range() {
+            Some(range) => {
+                self.marked.extend(range);
+                self.visual_anchor = None;
+            }
+            None => self.visual_anchor = self.state.selected(),
+        }
+    }
+
+    /// The visual range, from its anchor to the selected item in either direction.
+    pub fn visual_range(&self) -> Option<RangeInclusive<usize>> {
+        let (anchor, selected) = (self.visual_anchor?, self.state.selected()?);
+        Some(anchor.min(selected)..=anchor.max(selected))
+    }
+
+    pub fn mark_all(&mut self) {
+        self.marked = (0..self.items.len()).collect();
+        self.visual_anchor = None;
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.visual_anchor = None;
+    }
+
+    /// Whether the item at `index` is marked or in the visual range.
+    pub fn is_marked(&self, index: usize) -> bool {
+        self.marked.contains(&index) || self.visual_range().is_some_and(|r| r.contains(&index))
+    }
+
+    /// The marked items, including the visual range, in list order.
+    pub fn marked_items(&self) -> Vec<&T> {
+        let mut indexes = self.marked.clone();
+        indexes.extend(self.visual_range().into_iter().flatten());
+        indexes.iter().filter_map(|&i| self.items.get(i)).collect()
+    }
+
+    /// Replace the items, keeping the selected item selected if it is still there, as identified
+    /// by `key`. Returns whether the selection was kept.
+    pub fn replace_items_by<K, F>(&mut self, items: Vec<T>, key: F) -> bool
+    where
+        T: Clone,
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        // The filter keeps narrowing the new items
+        let items = match &mut self.filter {
+            Some(filter) => {
+                let selected = filter.selected.and_then(|i| filter.all.get(i)).map(&key);
+                filter.selected =
+                    selected.and_then(|k| items.iter().position(|item| key(item) == k));
+                filter.all = items;
+                filter.matching()
+            }
+            None => items,
+        };
+        let selected = self
+            .state
+            .selected()
+            .and_then(|i| self.items.get(i))
+            .map(&key);
+        let index = selected.and_then(|k| items.iter().position(|item| key(item) == k));
+        // Marks follow their items, and are dropped with them
+        let moved = |i: &usize| {
+            let k = key(self.items.get(*i)?);
+            items.iter().position(|item| key(item) == k)
+        };
+        self.marked = self.marked.iter().filter_map(moved).collect();
+        self.visual_anchor = self.visual_anchor.as_ref().and_then(moved);
+        self.items = items;
+        self.state.select(index);
+        index.is_some()
+    }
+}
+
+impl<T: Clone + PartialEq> StatefulList<T> {
+    /// Narrow the items to those `matches` accepts for `query`, ignoring case. The selected item
+    /// stays selected if it matches, else the first match is. Marks are cleared.
+    pub fn filter(&mut self, query: &str, matches: fn(&T, &str) -> bool) {
+        let filter = self.filter.get_or_insert_with(|| ListFilter {
+            query: String::new(),
+            all: self.items.clone(),
+            selected: self.state.selected(),
+            matches,
+        });
+        filter.query = query.to_string();
+        let selected = self
+            .state
+            .selected()
+            .and_then(|i| self.items.get(i))
+            .cloned();
+        self.items = filter.matching();
+        let index = selected.and_then(|item| self.items.iter().position(|i| *i == item));
+        self.state
+            .select(index.or((!self.items.is_empty()).then_some(0)));
+        self.clear_marks();
+    }
+
+    /// Drop the filter, restoring every item and the selection from before it was applied.
+    pub fn clear_filter(&mut self) {
+        if let Some(filter) = self.filter.take() {
+            self.items = filter.all;
+            self.state.select(filter.selected);
+            self.clear_marks();
+        }
+    }
+}
+
+/// Tracks which store calls are in flight, to show loading indicators in the pane titles.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Loading {
+    pub books: bool,
+    pub pages: bool,
+    pub content: bool,
+    pub sync: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
@@ -82,6 +299,11 @@ pub struct State {
     pub books: StatefulList<DnoteBook>,
     pub pages: StatefulList<DnotePage>,
     pub page_content: Option<String>,
+    pub loading: Loading,
+    /// Book to select when books are next loaded, e.g. after it was renamed or created.
+    pub pending_book: Option<String>,
+    /// Note to select when the active book's pages are next loaded, e.g. a search hit.
+    pub pending_page: Option<NoteId>,
 }
 
 impl State {
@@ -107,6 +329,26 @@ pub fn get_active_page(&self) -> Option<DnotePage> {
         }
     }
 
+    /// The pages bulk operations apply to: the marked ones, or else the active one.
+    pub fn get_target_pages(&self) -> Vec<DnotePage> {
+        match self.pages.marked_items() {
+            marked if marked.is_empty() => self.get_active_page().into_iter().collect(),
+            marked => marked.into_iter().cloned().collect(),
+        }
+    }
+
+    /// Whether `book_name` is still the active book, i.e. a response for it is not stale.
+    pub fn is_active_book(&self, book_name: &str) -> bool {
+        self.get_active_book()
+            .is_some_and(|book| book.name == book_name)
+    }
+
+    /// Whether `page_id` is still the active page, i.e. a response for it is not stale.
+    pub fn is_active_page(&self, page_id: NoteId) -> bool {
+        self.get_active_page()
+            .is_some_and(|page| page.id == page_id)
+    }
+
     pub fn select_next_book(&mut self) {
         self.books.next()
     }
@@ -124,7 +366,7 @@ pub fn select_prev_page(&mut self) {
     }
 
     pub fn select_book(&mut self, book: DnoteBook) {
-        if let Some(index) = self.books.items.iter().position(|b| *b == book) {
+        if let Some(index) = self.books.items.iter().position(|b| b.name == book.name) {
             self.books.state.select(Some(index));
         }
     }
@@ -135,6 +377,15 @@ pub fn select_page(&mut self, page: DnotePage) {
         }
     }
 
+    /// Select the page with `page_id`, returning whether the active book has it.
+    pub fn select_page_by_id(&mut self, page_id: NoteId) -> bool {
+        let index = self.pages.items.iter().position(|p| p.id == page_id);
+        if index.is_some() {
+            self.pages.state.select(index);
+        }
+        index.is_some()
+    }
+
     pub fn update_pages(&mut self, new_pages: Vec<DnotePage>) {
         // Create a map for the new pages
         let new_pages_map = new_pages
@@ -145,7 +396,7 @@ pub fn update_pages(&mut self, new_pages: Vec<DnotePage>) {
         // Update existing pages
         for page in self.pages.items.iter_mut() {
             if let Some(new_page) = new_pages_map.get(&page.id) {
-                page.summary.clone_from(&new_page.summary);
+                page.clone_from(new_page);
             }
         }
 
@@ -163,6 +414,102 @@ pub fn update_pages(&mut self, new_pages: Vec<DnotePage>) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn stateful_list_replace_items_by_keeps_selected_item() {
+        let mut list = StatefulList::with_items(vec![1, 2, 3]);
+        list.state.select(Some(1));
+        assert!(list.replace_items_by(vec![0, 1, 2, 3], |i| *i));
+        assert_eq!(list.state.selected(), Some(2));
+        assert!(!list.replace_items_by(vec![4, 5], |i| *i));
+        assert_eq!(list.state.selected(), None);
+        assert!(!list.replace_items_by(vec![4], |i| *i));
+        assert_eq!(list.state.selected(), None);
+    }
+
+    #[test]
+    fn stateful_list_marks_items_and_visual_ranges() {
+        let mut list = StatefulList::with_items(vec![1, 2, 3, 4, 5]);
+        list.state.select(Some(0));
+        list.toggle_mark();
+        list.state.select(Some(3));
+        list.toggle_visual();
+        list.state.select(Some(2));
+        assert_eq!(list.marked_items(), vec![&1, &3, &4]);
+        list.toggle_visual();
+        assert_eq!(list.visual_anchor, None);
+        list.toggle_mark();
+        assert_eq!(list.marked_items(), vec![&1, &4]);
+        assert!(list.is_marked(3) && !list.is_marked(2));
+        list.mark_all();
+        assert_eq!(list.marked_items().len(), 5);
+        list.clear_marks();
+        assert!(list.marked_items().is_empty());
+    }
+
+    #[test]
+    fn stateful_list_replace_items_by_keeps_marks_on_their_items() {
+        let mut list = StatefulList::with_items(vec![1, 2, 3]);
+        list.marked.extend([0, 2]);
+        list.replace_items_by(vec![0, 1, 2], |i| *i);
+        assert_eq!(list.marked_items(), vec![&1]);
+    }
+
+    #[test]
+    fn stateful_list_filters_items_and_restores_them() {
+        let words = ["Rust", "docker", "rustup", "go"]
+            .map(String::from)
+            .to_vec();
+        let mut list = StatefulList::with_items(words);
+        list.state.select(Some(1));
+        let contains = |word: &String, query: &str| word.to_lowercase().contains(query);
+        list.filter("RU", contains);
+        assert_eq!(list.items, vec!["Rust", "rustup"]);
+        assert_eq!(list.state.selected(), Some(0));
+        assert_eq!((list.filter_query(), list.total_len()), (Some("RU"), 4));
+        list.next_wrapping();
+        list.filter("rust", contains);
+        assert_eq!(list.state.selected(), Some(1));
+        list.next_wrapping();
+        assert_eq!(list.state.selected(), Some(0));
+        list.previous_wrapping();
+        assert_eq!(list.state.selected(), Some(1));
+        list.filter("nothing", contains);
+        assert_eq!(list.state.selected(), None);
+
+        list.filter("o", contains);
+        let reloaded = ["docker", "go", "rust"].map(String::from).to_vec();
+        list.replace_items_by(reloaded, |word| word.clone());
+        assert_eq!(list.items, vec!["docker", "go"]);
+        list.clear_filter();
+        assert_eq!(list.items, vec!["docker", "go", "rust"]);
+        assert_eq!(list.state.selected(), Some(0));
+        assert_eq!(list.filter_query(), None);
+    }
+
+    #[test]
+    fn state_targets_marked_pages_or_else_the_active_one() {
+        let mut state = State::new();
+        let pages = (1..=3)
+            .map(|id| DnotePage {
+                id,
+                ..Default::default()
+            })
+            .collect();
+        state.pages = StatefulList::with_items(pages);
+        assert!(state.get_target_pages().is_empty());
+        state.pages.state.select(Some(1));
+        let ids = |state: &State| {
+            state
+                .get_target_pages()
+                .iter()
+                .map(|p| p.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&state), vec![2]);
+        state.pages.marked.extend([0, 2]);
+        assert_eq!(ids(&state), vec![1, 3]);
+    }
+
     #[test]
     fn stateful_list_with_items_creates_list_with_given_items() {
         let items = vec![1, 2, 3];
@@ -276,14 +623,7 @@ fn state_new_creates_default_state() {
 
     #[test]
     fn state_get_active_book_returns_selected_book() {
-        let books = vec![
-            DnoteBook {
-                name: String::from("Book1"),
-            },
-            DnoteBook {
-                name: String::from("Book2"),
-            },
-        ];
+        let books = vec![DnoteBook::new("Book1"), DnoteBook::new("Book2")];
         let mut state = State::new();
         state.books = StatefulList::with_items(books.clone());
 
@@ -303,10 +643,12 @@ fn state_get_active_page_returns_selected_page() {
             DnotePage {
                 id: 1,
                 summary: String::from("Page1"),
+                ..Default::default()
             },
             DnotePage {
                 id: 2,
                 summary: String::from("Page2"),
+                ..Default::default()
             },
         ];
         let mut state = State::new();
@@ -322,16 +664,29 @@ fn state_get_active_page_returns_selected_page() {
         assert_eq!(state.get_active_page(), None);
     }
 
+    #[test]
+    fn state_is_active_book_and_page_match_selection() {
+        let mut state = State::new();
+        state.books = StatefulList::with_items(vec![DnoteBook::new("Book1")]);
+        state.pages = StatefulList::with_items(vec![DnotePage {
+            id: 7,
+            summary: String::from("Page7"),
+            ..Default::default()
+        }]);
+        assert!(!state.is_active_book("Book1"));
+        assert!(!state.is_active_page(7));
+
+        state.books.state.select(Some(0));
+        state.pages.state.select(Some(0));
+        assert!(state.is_active_book("Book1"));
+        assert!(!state.is_active_book("Book2"));
+        assert!(state.is_active_page(7));
+        assert!(!state.is_active_page(8));
+    }
+
     #[test]
     fn state_select_next_book_navigates_correctly() {
-        let books = vec![
-            DnoteBook {
-                name: String::from("Book1"),
-            },
-            DnoteBook {
-                name: String::from("Book2"),
-            },
-        ];
+        let books = vec![DnoteBook::new("Book1"), DnoteBook::new("Book2")];
         let mut state = State::new();
         state.books = StatefulList::with_items(books);
 
@@ -347,14 +702,7 @@ fn state_select_next_book_navigates_correctly() {
 
     #[test]
     fn state_select_prev_book_navigates_correctly() {
-        let books = vec![
-            DnoteBook {
-                name: String::from("Book1"),
-            },
-            DnoteBook {
-                name: String::from("Book2"),
-            },
-        ];
+        let books = vec![DnoteBook::new("Book1"), DnoteBook::new("Book2")];
         let mut state = State::new();
         state.books = StatefulList::with_items(books);
 
@@ -371,10 +719,12 @@ fn state_select_next_page_navigates_correctly() {
             DnotePage {
                 id: 1,
                 summary: String::from("Page1"),
+                ..Default::default()
             },
             DnotePage {
                 id: 2,
                 summary: String::from("Page2"),
+                ..Default::default()
             },
         ];
         let mut state = State::new();
@@ -396,10 +746,12 @@ fn state_select_prev_page_navigates_correctly() {
             DnotePage {
                 id: 1,
                 summary: String::from("Page1"),
+                ..Default::default()
             },
             DnotePage {
                 id: 2,
                 summary: String::from("Page2"),
+                ..Default::default()
             },
         ];
         let mut state = State::new();
@@ -414,25 +766,14 @@ fn state_select_prev_page_navigates_correctly() {
 
     #[test]
     fn state_select_book_selects_correct_book() {
-        let books = vec![
-            DnoteBook {
-                name: String::from("Book1"),
-            },
-            DnoteBook {
-                name: String::from("Book2"),
-            },
-        ];
+        let books = vec![DnoteBook::new("Book1"), DnoteBook::new("Book2")];
         let mut state = State::new();
         state.books = StatefulList::with_items(books.clone());
 
-        state.select_book(DnoteBook {
-            name: String::from("Book2"),
-        });
+        state.select_book(DnoteBook::new("Book2"));
         assert_eq!(state.books.state.selected(), Some(1));
 
-        state.select_book(DnoteBook {
-            name: String::from("Book1"),
-        });
+        state.select_book(DnoteBook::new("Book1"));
         assert_eq!(state.books.state.selected(), Some(0));
     }
 
@@ -442,10 +783,12 @@ fn state_select_page_selects_correct_page() {
             DnotePage {
                 id: 1,
                 summary: String::from("Page1"),
+                ..Default::default()
             },
             DnotePage {
                 id: 2,
                 summary: String::from("Page2"),
+                ..Default::default()
             },
         ];
         let mut state = State::new();
@@ -454,12 +797,14 @@ fn state_select_page_selects_correct_page() {
         state.select_page(DnotePage {
             id: 2,
             summary: String::from("Page2"),
+            ..Default::default()
         });
         assert_eq!(state.pages.state.selected(), Some(1));
 
         state.select_page(DnotePage {
             id: 1,
             summary: String::from("Page1"),
+            ..Default::default()
         });
         assert_eq!(state.pages.state.selected(), Some(0));
     }
@@ -470,20 +815,24 @@ fn state_updates_existing_pages_correctly() {
             DnotePage {
                 id: 1,
                 summary: String::from("Initial Page 1"),
+                ..Default::default()
             },
             DnotePage {
                 id: 2,
                 summary: String::from("Initial Page 2"),
+                ..Default::default()
             },
         ];
         let new_pages = vec![
             DnotePage {
                 id: 1,
                 summary: String::from("Updated Page 1"),
+                ..Default::default()
             },
             DnotePage {
                 id: 2,
                 summary: String::from("Updated Page 2"),
+                ..Default::default()
             },
         ];
 
@@ -502,15 +851,18 @@ fn state_adds_new_pages_correctly() {
         let initial_pages = vec![DnotePage {
             id: 1,
             summary: String::from("Initial Page 1"),
+            ..Default::default()
         }];
         let new_pages = vec![
             DnotePage {
                 id: 1,
                 summary: String::from("Updated Page 1"),
+                ..Default::default()
             },
             DnotePage {
                 id: 2,
                 summary: String::from("New Page 2"),
+                ..Default::default()
             },
         ];
 
@@ -530,15 +882,18 @@ fn state_keeps_existing_pages_not_in_new_pages() {
             DnotePage {
                 id: 1,
                 summary: String::from("Initial Page 1"),
+                ..Default::default()
             },
             DnotePage {
                 id: 2,
                 summary: String::from("Initial Page 2"),
+                ..Default::default()
             },
         ];
         let new_pages = vec![DnotePage {
             id: 1,
             summary: String::from("Updated Page 1"),
+            ..Default::default()
         }];
 
         let mut state = State::new();
@@ -0,0 +1,420 @@
+use std::{fs, path::Path, sync::Arc};
+
+use color_eyre::eyre::{eyre, Report, Result};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    action::Action,
+    config::{AppConfig, Backend},
+    dnote::{Dnote, DnoteBook, DnotePage, DnotePageInfo, NoteId},
+    search::{self, SearchHit},
+};
+
+pub mod cache;
+pub mod memory;
+pub mod sqlite;
+
+/// A note store shared between the app and its components.
+pub type SharedStore = Arc<dyn NoteStore>;
+
+/// `NoteStore` is the backend that books and notes are read from and written to.
+///
+/// The dnote CLI ([`crate::dnote::Dnote`]) is the default implementation. Other backends (e.g.
+/// [`sqlite::SqliteStore`] for the CLI's database, or [`memory::MemoryStore`] for tests and
+/// fixture data) can be swapped in without touching the components, and wrapped in a
+/// [`cache::CachedStore`].
+pub trait NoteStore: Send + Sync {
+    /// List all books.
+    fn get_books(&self) -> Result<Vec<DnoteBook>>;
+    /// List the notes of a book.
+    fn get_pages(&self, book_name: &str) -> Result<Vec<DnotePage>>;
+    /// Read the content of a note.
+    fn get_page_content(&self, page_id: NoteId) -> Result<DnotePageInfo>;
+    /// Read a note along with all the metadata the backend has for it.
+    fn get_page(&self, page_id: NoteId) -> Result<DnotePage>;
+    /// Add a note to a book, creating the book if it does not exist.
+    fn add_note(&self, book_name: &str, content: &str) -> Result<()>;
+    /// Replace the content of a note.
+    fn edit_note(&self, page_id: NoteId, content: &str) -> Result<()>;
+    /// Move a note to another book, creating the book if it does not exist.
+    fn move_note(&self, page_id: NoteId, book_name: &str) -> Result<()>;
+    /// Remove a note.
+    fn remove_note(&self, page_id: NoteId) -> Result<()>;
+    /// Rename a book.
+    fn rename_book(&self, book_name: &str, new_name: &str) -> Result<()>;
+    /// Remove a book along with all of its notes.
+    fn remove_book(&self, book_name: &str) -> Result<()>;
+    /// Sync notes with the dnote server. Errors are meant to be shown to the user as is.
+    fn sync(&self) -> Result<()> {
+        Err(eyre!("Syncing is not supported by this backend"))
+    }
+    /// Drop anything the store cached, so that the next reads hit the backend.
+    fn clear_cache(&self) -> Result<()> {
+        Ok(())
+    }
+    /// Search the content of all notes for `query`, ignoring case, returning at most
+    /// [`search::MAX_RESULTS`] hits.
+    ///
+    /// The default reads every note of every book, backends that can search natively override it.
+    fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
+        let mut hits = Vec::new();
+        if query.trim().is_empty() {
+            return Ok(hits);
+        }
+        for book in self.get_books()? {
+            for page in self.get_pages(&book.name)? {
+                let content = self.get_page_content(page.id)?.content;
+                if let Some(snippet) = search::snippet(&content, query) {
+                    hits.push(SearchHit {
+                        book_name: book.name.clone(),
+                        id: page.id,
+                        snippet,
+                    });
+                    if hits.len() == search::MAX_RESULTS {
+                        return Ok(hits);
+                    }
+                }
+            }
+        }
+        Ok(hits)
+    }
+}
+
+/// Open the note store selected by the `backend` config key, cached unless `cache` is off.
+pub fn open(config: &AppConfig) -> Result<SharedStore> {
+    let store: SharedStore = match config.backend {
+        Backend::Cli => Arc::new(Dnote::with_config(config.dnote.clone())),
+        Backend::Sqlite => {
+            let path = config
+                .dnote_db_path
+                .clone()
+                .or_else(|| sqlite::default_db_path(&config.dnote))
+                .ok_or_else(|| eyre!("Unable to locate dnote.db, set `dnote_db_path`"))?;
+            let store = sqlite::SqliteStore::open(path)?
+                .with_sync(Dnote::with_config(config.dnote.clone()));
+            Arc::new(store)
+        }
+    };
+    if config.cache {
+        return Ok(Arc::new(cache::CachedStore::new(store)));
+    }
+    Ok(store)
+}
+
+/// Book names dnote reserves for its own use.
+const RESERVED_BOOK_NAMES: [&str; 2] = ["trash", "conflicts"];
+
+/// Check `name` against dnote's rules for book names.
+pub fn validate_book_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        return Err(eyre!("Book name cannot be empty"));
+    }
+    if name.contains(char::is_whitespace) {
+        return Err(eyre!("Book name cannot contain spaces"));
+    }
+    if name.parse::<i64>().is_ok() {
+        return Err(eyre!("Book name cannot be a number"));
+    }
+    if RESERVED_BOOK_NAMES.contains(&name) {
+        return Err(eyre!("Book name `{}` is reserved", name));
+    }
+    Ok(())
+}
+
+/// Check that a note has some content, dnote doesn't allow empty notes.
+pub fn validate_content(content: &str) -> Result<()> {
+    if content.trim().is_empty() {
+        return Err(eyre!("Note content cannot be empty"));
+    }
+    Ok(())
+}
+
+/// Validate and apply a mutation action (see [`Action::is_mutation`]) to `store`.
+///
+/// Returns an [`Action::NotesChanged`] describing the change, along with the book to select once
+/// books are reloaded.
+pub fn apply_mutation(store: &dyn NoteStore, action: Action) -> Result<Action> {
+    let (message, book) = match action {
+        Action::AddNote { book, content } => {
+            validate_book_name(&book)?;
+            validate_content(&content)?;
+            store.add_note(&book, &content)?;
+            (format!("Added note to {book}"), Some(book))
+        }
+        Action::EditNote { id, content } => {
+            validate_content(&content)?;
+            store.edit_note(id, &content)?;
+            (format!("Updated note {id}"), None)
+        }
+        Action::MoveNote { id, book } => {
+            validate_book_name(&book)?;
+            store.move_note(id, &book)?;
+            (format!("Moved note {id} to {book}"), None)
+        }
+        Action::RenameBook { from, to } => {
+            validate_book_name(&to)?;
+            if from == to {
+                return Err(eyre!("Book is already named {}", to));
+            }
+            store.rename_book(&from, &to)?;
+            (format!("Renamed book {from} to {to}"), Some(to))
+        }
+        Action::DeleteNote(id) => {
+            store.remove_note(id)?;
+            (format!("Deleted note {id}"), None)
+        }
+        Action::DeleteBook(book) => {
+            store.remove_book(&book)?;
+            (format!("Deleted book {book}"), None)
+        }
+        Action::MoveNotes { ids, book } => {
+            validate_book_name(&book)?;
+            for_each_note(&ids, |id| store.move_note(id, &book)).map_err(|(done, err)| {
+                eyre!(
+                    "Moved {done} of {} to {book}: {err}",
+                    count_notes(ids.len())
+                )
+            })?;
+            (format!("Moved {} to {book}", count_notes(ids.len())), None)
+        }
+        Action::DeleteNotes(ids) => {
+            for_each_note(&ids, |id| store.remove_note(id)).map_err(|(done, err)| {
+                eyre!("Deleted {done} of {}: {err}", count_notes(ids.len()))
+            })?;
+            (format!("Deleted {}", count_notes(ids.len())), None)
+        }
+        action => return Err(eyre!("{} does not change notes", action)),
+    };
+    Ok(Action::NotesChanged { message, book })
+}
+
+/// Run `f` on every note of `ids`, carrying on past failures so one bad note doesn't stop the
+/// rest. Fails with the number of notes done and the first error.
+fn for_each_note(
+    ids: &[NoteId],
+    mut f: impl FnMut(NoteId) -> Result<()>,
+) -> Result<(), (usize, Report)> {
+    let mut done = 0;
+    let mut first_err = None;
+    for id in ids {
+        match f(*id) {
+            Ok(()) => done += 1,
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    match first_err {
+        Some(err) => Err((done, err)),
+        None => Ok(()),
+    }
+}
+
+/// Write the notes `ids` to a markdown file at `path`, one after the other, separated by rules.
+pub fn export_notes(store: &dyn NoteStore, ids: &[NoteId], path: &Path) -> Result<Action> {
+    let mut notes = Vec::new();
+    for id in ids {
+        notes.push(store.get_page_content(*id)?.content.trim_end().to_string());
+    }
+    fs::write(path, notes.join("\n\n---\n\n") + "\n")
+        .map_err(|err| eyre!("Failed to export to {}: {err}", path.display()))?;
+    Ok(Action::NotesExported {
+        count: ids.len(),
+        path: path.to_path_buf(),
+    })
+}
+
+/// "1 note" or "n notes".
+pub fn count_notes(count: usize) -> String {
+    match count {
+        1 => "1 note".to_string(),
+        n => format!("{n} notes"),
+    }
+}
+
+/// Run a store call on tokio's blocking thread pool and send the action it returns, so that slow
+/// backends never block the render loop. Failures are sent as [`Action::Error`].
+pub fn spawn_task<F>(store: SharedStore, tx: UnboundedSender<Action>, task: F)
+where
+    F: FnOnce(&dyn NoteStore) -> Result<Action> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let action = task(store.as_ref()).unwrap_or_else(|err| {
+            log::error!("Store task failed: {err:?}");
+            Action::Error(err.to_string())
+        });
+        // The receiver is only gone when the app is shutting down
+        let _ = tx.send(action);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{memory::MemoryStore, *};
+
+    #[test]
+    fn validate_book_name_follows_dnote_rules() {
+        assert!(validate_book_name("rust").is_ok());
+        assert!(validate_book_name("notes-日本語").is_ok());
+        for name in ["", "two words", "42", "trash", "conflicts"] {
+            assert!(
+                validate_book_name(name).is_err(),
+                "{name:?} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn apply_mutation_changes_store_and_reports_book_to_select() {
+        let store = MemoryStore::with_notes([("rust", "cargo")]).unwrap();
+        let changed = apply_mutation(
+            &store,
+            Action::AddNote {
+                book: "go".into(),
+                content: "gofmt".into(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            changed,
+            Action::NotesChanged {
+                message: "Added note to go".into(),
+                book: Some("go".into()),
+            }
+        );
+        let changed = apply_mutation(
+            &store,
+            Action::RenameBook {
+                from: "rust".into(),
+                to: "rustlang".into(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            changed,
+            Action::NotesChanged {
+                message: "Renamed book rust to rustlang".into(),
+                book: Some("rustlang".into()),
+            }
+        );
+        apply_mutation(&store, Action::DeleteNote(2)).unwrap();
+        assert!(store.get_pages("go").unwrap().is_empty());
+    }
+
+    #[test]
+    fn apply_mutation_rejects_invalid_input() {
+        let store = MemoryStore::with_notes([("rust", "cargo")]).unwrap();
+        let invalid = [
+            Action::AddNote {
+                book: "rust".into(),
+                content: "  \n".into(),
+            },
+            Action::MoveNote {
+                id: 1,
+                book: "my book".into(),
+            },
+            Action::RenameBook {
+                from: "rust".into(),
+                to: "rust".into(),
+            },
+            Action::DeleteNote(42),
+            Action::LoadBooks,
+        ];
+        for action in invalid {
+            assert!(
+                apply_mutation(&store, action.clone()).is_err(),
+                "{action:?}"
+            );
+        }
+        assert_eq!(store.get_page_content(1).unwrap().content, "cargo");
+    }
+
+    #[test]
+    fn apply_mutation_moves_and_deletes_notes_in_bulk() {
+        let store =
+            MemoryStore::with_notes([("rust", "cargo"), ("rust", "clippy"), ("rust", "doc")])
+                .unwrap();
+        let moved = Action::MoveNotes {
+            ids: vec![1, 3],
+            book: "tools".into(),
+        };
+        assert_eq!(
+            apply_mutation(&store, moved).unwrap(),
+            Action::NotesChanged {
+                message: "Moved 2 notes to tools".into(),
+                book: None,
+            }
+        );
+        assert_eq!(store.get_pages("tools").unwrap().len(), 2);
+        apply_mutation(&store, Action::DeleteNotes(vec![1, 3])).unwrap();
+        assert!(store.get_pages("tools").unwrap().is_empty());
+        assert_eq!(store.get_pages("rust").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn apply_mutation_carries_on_past_a_failing_note_and_counts_the_rest() {
+        let store =
+            MemoryStore::with_notes([("rust", "cargo"), ("rust", "clippy"), ("rust", "doc")])
+                .unwrap();
+        let moved = Action::MoveNotes {
+            ids: vec![1, 42, 3],
+            book: "tools".into(),
+        };
+        let err = apply_mutation(&store, moved).unwrap_err().to_string();
+        assert!(
+            err.starts_with("Moved 2 of 3 notes to tools: "),
+            "unexpected error {err:?}"
+        );
+        assert_eq!(store.get_pages("tools").unwrap().len(), 2);
+        let err = apply_mutation(&store, Action::DeleteNotes(vec![1, 42, 3]))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("Deleted 2 of 3 notes: "),
+            "unexpected error {err:?}"
+        );
+        assert!(store.get_pages("tools").unwrap().is_empty());
+    }
+
+    #[test]
+    fn export_notes_writes_them_to_one_file() {
+        let store = MemoryStore::with_notes([("rust", "cargo\n"), ("rust", "clippy")]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rust.md");
+        assert_eq!(
+            export_notes(&store, &[2, 1], &path).unwrap(),
+            Action::NotesExported {
+                count: 2,
+                path: path.clone(),
+            }
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "clippy\n\n---\n\ncargo\n"
+        );
+        assert!(export_notes(&store, &[1], &dir.path().join("no/such/dir.md")).is_err());
+    }
+
+    #[test]
+    fn default_search_reads_every_book() {
+        let store = MemoryStore::with_notes([
+            ("rust", "# Ownership\n\nBorrowing rules"),
+            ("docker", "compose up"),
+            ("rust", "cargo clippy"),
+        ])
+        .unwrap();
+        assert_eq!(
+            store.search("borrow").unwrap(),
+            vec![SearchHit {
+                book_name: "rust".into(),
+                id: 1,
+                snippet: "Borrowing rules".into(),
+            }]
+        );
+        let ids: Vec<_> = store.search("O").unwrap().iter().map(|h| h.id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+        assert!(store.search("  ").unwrap().is_empty());
+    }
+}
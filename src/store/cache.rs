@@ -0,0 +1,381 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use color_eyre::eyre::{eyre, Result};
+
+use super::{NoteStore, SharedStore};
+use crate::{
+    dnote::{DnoteBook, DnotePage, DnotePageInfo, NoteId},
+    search::SearchHit,
+};
+
+#[derive(Debug, Default)]
+struct Cache {
+    books: Option<Vec<DnoteBook>>,
+    pages: HashMap<String, Vec<DnotePage>>,
+    contents: HashMap<NoteId, DnotePageInfo>,
+    /// Bumped on every invalidation, so that a read that raced with a write doesn't cache what
+    /// it read before the write.
+    generation: u64,
+}
+
+impl Cache {
+    /// The book a note is listed in, if that book's pages are cached.
+    fn book_of(&self, page_id: NoteId) -> Option<String> {
+        self.pages
+            .iter()
+            .find(|(_, pages)| pages.iter().any(|p| p.id == page_id))
+            .map(|(book_name, _)| book_name.clone())
+    }
+
+    fn invalidate_books(&mut self) {
+        self.books = None;
+        self.generation += 1;
+    }
+
+    fn invalidate_pages(&mut self, book_name: &str) {
+        self.pages.remove(book_name);
+        self.generation += 1;
+    }
+
+    fn invalidate_content(&mut self, page_id: NoteId) {
+        self.contents.remove(&page_id);
+        self.generation += 1;
+    }
+
+    /// Invalidate the pages of the book `page_id` is listed in.
+    fn invalidate_pages_of(&mut self, page_id: NoteId) {
+        if let Some(book_name) = self.book_of(page_id) {
+            self.invalidate_pages(&book_name);
+        }
+    }
+}
+
+/// `CachedStore` is a [`NoteStore`] that caches the books, page lists and note contents read
+/// from another store.
+///
+/// Writes go through to the wrapped store and only invalidate the entries they affect, e.g.
+/// editing a note invalidates its content, the page list of its book and the book list, which
+/// carries note counts and last edits. Note metadata read
+/// with [`NoteStore::get_page`] is never cached.
+pub struct CachedStore {
+    inner: SharedStore,
+    cache: Mutex<Cache>,
+}
+
+impl CachedStore {
+    pub fn new(inner: SharedStore) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Cache>> {
+        self.cache
+            .lock()
+            .map_err(|_| eyre!("Store cache lock poisoned"))
+    }
+
+    /// Read through the cache: return the entry found by `get`, or else load it from the wrapped
+    /// store without holding the lock and store it with `put`.
+    fn read_through<T: Clone>(
+        &self,
+        get: impl Fn(&Cache) -> Option<T>,
+        load: impl FnOnce(&dyn NoteStore) -> Result<T>,
+        put: impl FnOnce(&mut Cache, T),
+    ) -> Result<T> {
+        let generation = {
+            let cache = self.lock()?;
+            if let Some(value) = get(&cache) {
+                return Ok(value);
+            }
+            cache.generation
+        };
+        let value = load(self.inner.as_ref())?;
+        let mut cache = self.lock()?;
+        if cache.generation == generation {
+            put(&mut cache, value.clone());
+        }
+        Ok(value)
+    }
+}
+
+impl NoteStore for CachedStore {
+    fn get_books(&self) -> Result<Vec<DnoteBook>> {
+        self.read_through(
+            |cache| cache.books.clone(),
+            |store| store.get_books(),
+            |cache, books| cache.books = Some(books),
+        )
+    }
+
+    fn get_pages(&self, book_name: &str) -> Result<Vec<DnotePage>> {
+        self.read_through(
+            |cache| cache.pages.get(book_name).cloned(),
+            |store| store.get_pages(book_name),
+            |cache, pages| {
+                cache.pages.insert(book_name.to_string(), pages);
+            },
+        )
+    }
+
+    fn get_page_content(&self, page_id: NoteId) -> Result<DnotePageInfo> {
+        self.read_through(
+            |cache| cache.contents.get(&page_id).cloned(),
+            |store| store.get_page_content(page_id),
+            |cache, content| {
+                cache.contents.insert(page_id, content);
+            },
+        )
+    }
+
+    fn get_page(&self, page_id: NoteId) -> Result<DnotePage> {
+        self.inner.get_page(page_id)
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
+        // Searching natively beats reading every note, even from the cache
+        self.inner.search(query)
+    }
+
+    fn add_note(&self, book_name: &str, content: &str) -> Result<()> {
+        let result = self.inner.add_note(book_name, content);
+        let mut cache = self.lock()?;
+        cache.invalidate_pages(book_name);
+        // The book may be new, and its note count changed either way
+        cache.invalidate_books();
+        result
+    }
+
+    fn edit_note(&self, page_id: NoteId, content: &str) -> Result<()> {
+        let result = self.inner.edit_note(page_id, content);
+        let mut cache = self.lock()?;
+        cache.invalidate_content(page_id);
+        // The summary shown in the page list and the book's last edit may have changed
+        cache.invalidate_pages_of(page_id);
+        cache.invalidate_books();
+        result
+    }
+
+    fn move_note(&self, page_id: NoteId, book_name: &str) -> Result<()> {
+        let result = self.inner.move_note(page_id, book_name);
+        let mut cache = self.lock()?;
+        cache.invalidate_pages_of(page_id);
+        cache.invalidate_pages(book_name);
+        cache.invalidate_books();
+        result
+    }
+
+    fn remove_note(&self, page_id: NoteId) -> Result<()> {
+        let result = self.inner.remove_note(page_id);
+        let mut cache = self.lock()?;
+        cache.invalidate_content(page_id);
+        cache.invalidate_pages_of(page_id);
+        cache.invalidate_books();
+        result
+    }
+
+    fn rename_book(&self, book_name: &str, new_name: &str) -> Result<()> {
+        let result = self.inner.rename_book(book_name, new_name);
+        let mut cache = self.lock()?;
+        cache.invalidate_books();
+        cache.invalidate_pages(book_name);
+        cache.invalidate_pages(new_name);
+        result
+    }
+
+    fn remove_book(&self, book_name: &str) -> Result<()> {
+        let result = self.inner.remove_book(book_name);
+        let mut cache = self.lock()?;
+        let page_ids: Vec<_> = cache
+            .pages
+            .get(book_name)
+            .map(|pages| pages.iter().map(|p| p.id).collect())
+            .unwrap_or_default();
+        for page_id in page_ids {
+            cache.invalidate_content(page_id);
+        }
+        cache.invalidate_books();
+        cache.invalidate_pages(book_name);
+        result
+    }
+
+    fn sync(&self) -> Result<()> {
+        let result = self.inner.sync();
+        // A sync can change anything, even when it fails halfway
+        self.clear_cache()?;
+        result
+    }
+
+    fn clear_cache(&self) -> Result<()> {
+        let mut cache = self.lock()?;
+        let generation = cache.generation;
+        *cache = Cache {
+            generation: generation + 1,
+            ..Default::default()
+        };
+        self.inner.clear_cache()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::store::memory::MemoryStore;
+
+    /// Counts the reads that reach the wrapped store.
+    #[derive(Default)]
+    struct Counting {
+        store: MemoryStore,
+        reads: AtomicUsize,
+    }
+
+    impl Counting {
+        fn read(&self) {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl NoteStore for Counting {
+        fn get_books(&self) -> Result<Vec<DnoteBook>> {
+            self.read();
+            self.store.get_books()
+        }
+        fn get_pages(&self, book_name: &str) -> Result<Vec<DnotePage>> {
+            self.read();
+            self.store.get_pages(book_name)
+        }
+        fn get_page_content(&self, page_id: NoteId) -> Result<DnotePageInfo> {
+            self.read();
+            self.store.get_page_content(page_id)
+        }
+        fn get_page(&self, page_id: NoteId) -> Result<DnotePage> {
+            self.store.get_page(page_id)
+        }
+        fn add_note(&self, book_name: &str, content: &str) -> Result<()> {
+            self.store.add_note(book_name, content)
+        }
+        fn edit_note(&self, page_id: NoteId, content: &str) -> Result<()> {
+            self.store.edit_note(page_id, content)
+        }
+        fn move_note(&self, page_id: NoteId, book_name: &str) -> Result<()> {
+            self.store.move_note(page_id, book_name)
+        }
+        fn remove_note(&self, page_id: NoteId) -> Result<()> {
+            self.store.remove_note(page_id)
+        }
+        fn rename_book(&self, book_name: &str, new_name: &str) -> Result<()> {
+            self.store.rename_book(book_name, new_name)
+        }
+        fn remove_book(&self, book_name: &str) -> Result<()> {
+            self.store.remove_book(book_name)
+        }
+    }
+
+    fn fixture() -> (Arc<Counting>, CachedStore) {
+        let counting = Arc::new(Counting {
+            store: MemoryStore::with_notes([
+                ("rust", "cargo"),
+                ("docker", "compose"),
+                ("rust", "clippy"),
+            ])
+            .unwrap(),
+            ..Default::default()
+        });
+        let cached = CachedStore::new(counting.clone());
+        (counting, cached)
+    }
+
+    fn reads(counting: &Counting) -> usize {
+        counting.reads.load(Ordering::SeqCst)
+    }
+
+    /// Read books, both page lists and every note content through the cache.
+    fn read_all(cached: &CachedStore) {
+        cached.get_books().unwrap();
+        for book in ["rust", "docker"] {
+            for page in cached.get_pages(book).unwrap() {
+                cached.get_page_content(page.id).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn cached_store_serves_repeated_reads_from_cache() {
+        let (counting, cached) = fixture();
+        read_all(&cached);
+        assert_eq!(reads(&counting), 6);
+        read_all(&cached);
+        assert_eq!(reads(&counting), 6);
+    }
+
+    #[test]
+    fn cached_store_edit_invalidates_the_note_and_its_book() {
+        let (counting, cached) = fixture();
+        read_all(&cached);
+        cached.edit_note(1, "cargo build").unwrap();
+        read_all(&cached);
+        // The note's content, the pages of `rust` and the books
+        assert_eq!(reads(&counting), 9);
+        assert_eq!(cached.get_page_content(1).unwrap().content, "cargo build");
+        assert_eq!(cached.get_pages("rust").unwrap()[0].summary, "cargo build");
+    }
+
+    #[test]
+    fn cached_store_rename_invalidates_books_and_the_renamed_book() {
+        let (counting, cached) = fixture();
+        read_all(&cached);
+        cached.rename_book("docker", "containers").unwrap();
+        cached.get_books().unwrap();
+        cached.get_pages("rust").unwrap();
+        cached.get_pages("containers").unwrap();
+        cached.get_page_content(2).unwrap();
+        assert_eq!(reads(&counting), 8);
+    }
+
+    #[test]
+    fn cached_store_add_invalidates_book_list_and_counts() {
+        let (counting, cached) = fixture();
+        read_all(&cached);
+        cached.add_note("rust", "rustfmt").unwrap();
+        let books = cached.get_books().unwrap();
+        assert_eq!(books[1].note_count, Some(3));
+        assert_eq!(cached.get_pages("rust").unwrap().len(), 3);
+        assert_eq!(reads(&counting), 8);
+        cached.add_note("go", "gofmt").unwrap();
+        assert_eq!(cached.get_books().unwrap().len(), 3);
+        assert_eq!(reads(&counting), 9);
+    }
+
+    #[test]
+    fn cached_store_move_and_remove_invalidate_affected_books() {
+        let (counting, cached) = fixture();
+        read_all(&cached);
+        cached.move_note(3, "docker").unwrap();
+        assert_eq!(cached.get_pages("rust").unwrap().len(), 1);
+        assert_eq!(cached.get_pages("docker").unwrap().len(), 2);
+        assert_eq!(reads(&counting), 8);
+        cached.remove_note(2).unwrap();
+        assert!(cached.get_page_content(2).is_err());
+        assert_eq!(cached.get_pages("docker").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn cached_store_clear_cache_reloads_everything() {
+        let (counting, cached) = fixture();
+        read_all(&cached);
+        cached.clear_cache().unwrap();
+        read_all(&cached);
+        assert_eq!(reads(&counting), 12);
+    }
+}
@@ -0,0 +1,300 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+use super::NoteStore;
+use crate::dnote::{summarize, DnoteBook, DnotePage, DnotePageInfo, NoteId};
+
+#[derive(Debug, Clone)]
+struct MemoryNote {
+    uuid: String,
+    book_name: String,
+    content: String,
+    added_on: DateTime<Utc>,
+    edited_on: Option<DateTime<Utc>>,
+}
+
+impl MemoryNote {
+    fn to_page(&self, id: NoteId) -> DnotePage {
+        DnotePage {
+            id,
+            summary: summarize(&self.content),
+            uuid: Some(self.uuid.clone()),
+            book_name: Some(self.book_name.clone()),
+            added_on: Some(self.added_on),
+            edited_on: self.edited_on,
+            public: Some(false),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    books: Vec<String>,
+    notes: BTreeMap<NoteId, MemoryNote>,
+    next_id: NoteId,
+}
+
+impl Inner {
+    fn ensure_book(&mut self, book_name: &str) {
+        if !self.books.iter().any(|b| b == book_name) {
+            self.books.push(book_name.to_string());
+            self.books.sort();
+        }
+    }
+
+    fn note_mut(&mut self, page_id: NoteId) -> Result<&mut MemoryNote> {
+        self.notes
+            .get_mut(&page_id)
+            .ok_or_else(|| eyre!("Note {} not found", page_id))
+    }
+}
+
+/// `MemoryStore` is a [`NoteStore`] that keeps books and notes in memory.
+///
+/// It is meant for tests and for running the TUI against fixture data. Note ids are assigned
+/// sequentially starting from 1, like row ids in dnote's database.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    inner: Mutex<Inner>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a store populated with `(book_name, content)` notes, in order.
+    pub fn with_notes<I, B, C>(notes: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (B, C)>,
+        B: AsRef<str>,
+        C: AsRef<str>,
+    {
+        let store = Self::new();
+        for (book_name, content) in notes {
+            store.add_note(book_name.as_ref(), content.as_ref())?;
+        }
+        Ok(store)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Inner>> {
+        self.inner
+            .lock()
+            .map_err(|_| eyre!("Memory store lock poisoned"))
+    }
+}
+
+impl NoteStore for MemoryStore {
+    fn get_books(&self) -> Result<Vec<DnoteBook>> {
+        let inner = self.lock()?;
+        let books = inner
+            .books
+            .iter()
+            .map(|name| {
+                let notes = inner.notes.values().filter(|note| note.book_name == *name);
+                DnoteBook {
+                    note_count: Some(notes.clone().count()),
+                    last_edited: notes
+                        .map(|note| note.edited_on.unwrap_or(note.added_on))
+                        .max(),
+                    ..DnoteBook::new(name.as_str())
+                }
+            })
+            .collect();
+        Ok(books)
+    }
+
+    fn get_pages(&self, book_name: &str) -> Result<Vec<DnotePage>> {
+        let inner = self.lock()?;
+        let pages = inner
+            .notes
+            .iter()
+            .filter(|(_, note)| note.book_name == book_name)
+            .map(|(id, note)| note.to_page(*id))
+            .collect();
+        Ok(pages)
+    }
+
+    fn get_page_content(&self, page_id: NoteId) -> Result<DnotePageInfo> {
+        let inner = self.lock()?;
+        let note = inner
+            .notes
+            .get(&page_id)
+            .ok_or_else(|| eyre!("Note {} not found", page_id))?;
+        Ok(DnotePageInfo {
+            content: note.content.clone(),
+        })
+    }
+
+    fn get_page(&self, page_id: NoteId) -> Result<DnotePage> {
+        let inner = self.lock()?;
+        let note = inner
+            .notes
+            .get(&page_id)
+            .ok_or_else(|| eyre!("Note {} not found", page_id))?;
+        Ok(note.to_page(page_id))
+    }
+
+    fn add_note(&self, book_name: &str, content: &str) -> Result<()> {
+        let mut inner = self.lock()?;
+        inner.ensure_book(book_name);
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.notes.insert(
+            id,
+            MemoryNote {
+                uuid: uuid::Uuid::new_v4().to_string(),
+                book_name: book_name.to_string(),
+                content: content.to_string(),
+                added_on: Utc::now(),
+                edited_on: None,
+            },
+        );
+        Ok(())
+    }
+
+    fn edit_note(&self, page_id: NoteId, content: &str) -> Result<()> {
+        let mut inner = self.lock()?;
+        let note = inner.note_mut(page_id)?;
+        note.content = content.to_string();
+        note.edited_on = Some(Utc::now());
+        Ok(())
+    }
+
+    fn move_note(&self, page_id: NoteId, book_name: &str) -> Result<()> {
+        let mut inner = self.lock()?;
+        inner.note_mut(page_id)?;
+        inner.ensure_book(book_name);
+        let note = inner.note_mut(page_id)?;
+        note.book_name = book_name.to_string();
+        note.edited_on = Some(Utc::now());
+        Ok(())
+    }
+
+    fn remove_note(&self, page_id: NoteId) -> Result<()> {
+        let mut inner = self.lock()?;
+        inner
+            .notes
+            .remove(&page_id)
+            .ok_or_else(|| eyre!("Note {} not found", page_id))?;
+        Ok(())
+    }
+
+    fn rename_book(&self, book_name: &str, new_name: &str) -> Result<()> {
+        let mut inner = self.lock()?;
+        if !inner.books.iter().any(|b| b == book_name) {
+            return Err(eyre!("Book {} not found", book_name));
+        }
+        if inner.books.iter().any(|b| b == new_name) {
+            return Err(eyre!("Book {} already exists", new_name));
+        }
+        inner.books.retain(|b| b != book_name);
+        inner.ensure_book(new_name);
+        for note in inner.notes.values_mut() {
+            if note.book_name == book_name {
+                note.book_name = new_name.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_book(&self, book_name: &str) -> Result<()> {
+        let mut inner = self.lock()?;
+        if !inner.books.iter().any(|b| b == book_name) {
+            return Err(eyre!("Book {} not found", book_name));
+        }
+        inner.books.retain(|b| b != book_name);
+        inner.notes.retain(|_, note| note.book_name != book_name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn fixture() -> MemoryStore {
+        MemoryStore::with_notes([
+            ("rust", "# Ownership\n\nborrowing rules"),
+            ("docker", "compose up"),
+            ("rust", "cargo clippy"),
+        ])
+        .unwrap()
+    }
+
+    fn book_names(store: &MemoryStore) -> Vec<String> {
+        store
+            .get_books()
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect()
+    }
+
+    #[test]
+    fn memory_store_lists_books_sorted_by_name() {
+        let store = fixture();
+        assert_eq!(book_names(&store), vec!["docker", "rust"]);
+        let counts: Vec<_> = store
+            .get_books()
+            .unwrap()
+            .into_iter()
+            .map(|b| (b.note_count, b.last_edited.is_some()))
+            .collect();
+        assert_eq!(counts, vec![(Some(1), true), (Some(2), true)]);
+    }
+
+    #[test]
+    fn memory_store_lists_pages_with_summaries() {
+        let store = fixture();
+        let pages = store.get_pages("rust").unwrap();
+        let summaries: Vec<_> = pages.iter().map(|p| (p.id, p.summary.as_str())).collect();
+        assert_eq!(summaries, vec![(1, "# Ownership"), (3, "cargo clippy")]);
+        assert_eq!(pages[0].book_name.as_deref(), Some("rust"));
+        assert_eq!(
+            store.get_page_content(1).unwrap().content,
+            "# Ownership\n\nborrowing rules"
+        );
+    }
+
+    #[test]
+    fn memory_store_edits_and_moves_notes() {
+        let store = fixture();
+        assert_eq!(store.get_page(2).unwrap().edited_on, None);
+        store.edit_note(2, "compose down").unwrap();
+        assert_eq!(store.get_page_content(2).unwrap().content, "compose down");
+        let page = store.get_page(2).unwrap();
+        assert_eq!(page.summary, "compose down");
+        assert!(page.edited_on >= page.added_on);
+
+        store.move_note(2, "ops").unwrap();
+        assert!(store.get_pages("docker").unwrap().is_empty());
+        assert_eq!(store.get_pages("ops").unwrap()[0].id, 2);
+        assert!(store.move_note(42, "ops").is_err());
+    }
+
+    #[test]
+    fn memory_store_renames_and_removes_books() {
+        let store = fixture();
+        store.rename_book("rust", "rustlang").unwrap();
+        assert_eq!(book_names(&store), vec!["docker", "rustlang"]);
+        assert_eq!(store.get_pages("rustlang").unwrap().len(), 2);
+        assert!(store.rename_book("docker", "rustlang").is_err());
+
+        store.remove_book("rustlang").unwrap();
+        assert_eq!(book_names(&store), vec!["docker"]);
+        assert!(store.get_page_content(1).is_err());
+    }
+
+    #[test]
+    fn memory_store_removes_notes() {
+        let store = fixture();
+        store.remove_note(1).unwrap();
+        assert_eq!(store.get_pages("rust").unwrap().len(), 1);
+        assert!(store.remove_note(1).is_err());
+    }
+}
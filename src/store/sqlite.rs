@@ -0,0 +1,524 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::eyre::{eyre, Result};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Transaction};
+
+use super::NoteStore;
+use crate::config::DnoteConfig;
+use crate::dnote::{
+    from_dnote_timestamp, summarize, Dnote, DnoteBook, DnotePage, DnotePageInfo, NoteId,
+};
+use crate::search::{self, SearchHit};
+
+/// Columns read by [`SqliteStore::row_to_page`].
+const PAGE_COLUMNS: &str = "notes.rowid, notes.body, notes.uuid, books.label, notes.added_on,
+    notes.edited_on, notes.public";
+
+/// Returns the location of the dnote CLI's database.
+///
+/// Follows the CLI's conventions: `$DNOTE_DIR/dnote.db` if set, else
+/// `$XDG_DATA_HOME/dnote/dnote.db` (defaulting to `~/.local/share/dnote/dnote.db`), falling back
+/// to the legacy `~/.dnote/dnote.db` if only that one exists. Environment overrides configured
+/// for dnote take precedence over the process environment.
+pub fn default_db_path(dnote: &DnoteConfig) -> Option<PathBuf> {
+    if let Some(dnote_dir) = dnote.env_var("DNOTE_DIR") {
+        return Some(PathBuf::from(dnote_dir).join("dnote.db"));
+    }
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    let data_home = dnote
+        .env_var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| home.as_ref().map(|h| h.join(".local").join("share")))?;
+    let xdg_path = data_home.join("dnote").join("dnote.db");
+    let legacy_path = home.map(|h| h.join(".dnote").join("dnote.db"));
+    match legacy_path {
+        Some(legacy) if !xdg_path.exists() && legacy.exists() => Some(legacy),
+        _ => Some(xdg_path),
+    }
+}
+
+fn now_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or_default()
+}
+
+/// `SqliteStore` is a [`NoteStore`] that reads and writes the dnote CLI's SQLite database
+/// directly instead of spawning a `dnote` process for every call.
+///
+/// Writes mirror the CLI: changed rows are flagged `dirty` and deletions are soft deletes, so
+/// that a later `dnote sync` uploads them. `usn` is left untouched, as it is only ever assigned
+/// by the server during a sync (new rows start at 0).
+///
+/// Syncing is delegated to the dnote CLI set with [`SqliteStore::with_sync`], which pushes the
+/// dirty rows and pulls changes into the same database.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+    sync_with: Option<Dnote>,
+}
+
+impl SqliteStore {
+    /// Open an existing dnote database. The database is never created, since its schema is owned
+    /// by the dnote CLI.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| eyre!("Failed to open dnote database {}: {}", path.display(), e))?;
+        Ok(Self::from_connection(conn))
+    }
+
+    pub fn from_connection(conn: Connection) -> Self {
+        Self {
+            conn: Mutex::new(conn),
+            sync_with: None,
+        }
+    }
+
+    /// Sync through `dnote`, which must be configured to use the same database.
+    pub fn with_sync(mut self, dnote: Dnote) -> Self {
+        self.sync_with = Some(dnote);
+        self
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| eyre!("SQLite store lock poisoned"))
+    }
+
+    fn find_book_uuid(tx: &Transaction, book_name: &str) -> Result<Option<String>> {
+        let uuid = tx
+            .query_row(
+                "SELECT uuid FROM books WHERE label = ?1 AND deleted = false",
+                params![book_name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(uuid)
+    }
+
+    fn get_or_create_book_uuid(tx: &Transaction, book_name: &str) -> Result<String> {
+        if let Some(uuid) = Self::find_book_uuid(tx, book_name)? {
+            return Ok(uuid);
+        }
+        let uuid = uuid::Uuid::new_v4().to_string();
+        tx.execute(
+            "INSERT INTO books (uuid, label, usn, dirty, deleted) VALUES (?1, ?2, 0, true, false)",
+            params![uuid, book_name],
+        )?;
+        Ok(uuid)
+    }
+
+    fn row_to_page(row: &rusqlite::Row) -> rusqlite::Result<DnotePage> {
+        let body: String = row.get(1)?;
+        Ok(DnotePage {
+            id: row.get(0)?,
+            summary: summarize(&body),
+            uuid: Some(row.get(2)?),
+            book_name: Some(row.get(3)?),
+            added_on: from_dnote_timestamp(row.get(4)?),
+            edited_on: from_dnote_timestamp(row.get::<_, Option<i64>>(5)?.unwrap_or_default()),
+            public: Some(row.get::<_, Option<bool>>(6)?.unwrap_or_default()),
+        })
+    }
+
+    fn ensure_updated(changed: usize, page_id: NoteId) -> Result<()> {
+        if changed == 0 {
+            return Err(eyre!("Note {} not found", page_id));
+        }
+        Ok(())
+    }
+}
+
+impl NoteStore for SqliteStore {
+    fn get_books(&self) -> Result<Vec<DnoteBook>> {
+        let conn = self.lock()?;
+        // A note never edited has an `edited_on` of 0, so the latest change is the larger one
+        let mut stmt = conn.prepare(
+            "SELECT books.label, COUNT(notes.uuid),
+                    MAX(MAX(notes.added_on, COALESCE(notes.edited_on, 0)))
+             FROM books
+             LEFT JOIN notes ON notes.book_uuid = books.uuid AND notes.deleted = false
+             WHERE books.deleted = false
+             GROUP BY books.uuid
+             ORDER BY books.label ASC",
+        )?;
+        let books = stmt
+            .query_map([], |row| {
+                Ok(DnoteBook {
+                    name: row.get(0)?,
+                    note_count: Some(row.get(1)?),
+                    last_edited: row.get::<_, Option<i64>>(2)?.and_then(from_dnote_timestamp),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(books)
+    }
+
+    fn get_pages(&self, book_name: &str) -> Result<Vec<DnotePage>> {
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {PAGE_COLUMNS} FROM notes
+             INNER JOIN books ON books.uuid = notes.book_uuid
+             WHERE books.label = ?1 AND books.deleted = false AND notes.deleted = false
+             ORDER BY notes.added_on ASC"
+        ))?;
+        let pages = stmt
+            .query_map(params![book_name], Self::row_to_page)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(pages)
+    }
+
+    fn get_page_content(&self, page_id: NoteId) -> Result<DnotePageInfo> {
+        let conn = self.lock()?;
+        let content = conn
+            .query_row(
+                "SELECT body FROM notes WHERE rowid = ?1 AND deleted = false",
+                params![page_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| eyre!("Note {} not found", page_id))?;
+        Ok(DnotePageInfo { content })
+    }
+
+    fn get_page(&self, page_id: NoteId) -> Result<DnotePage> {
+        let conn = self.lock()?;
+        let page = conn
+            .query_row(
+                &format!(
+                    "SELECT {PAGE_COLUMNS} FROM notes
+                     INNER JOIN books ON books.uuid = notes.book_uuid
+                     WHERE notes.rowid = ?1 AND notes.deleted = false"
+                ),
+                params![page_id],
+                Self::row_to_page,
+            )
+            .optional()?
+            .ok_or_else(|| eyre!("Note {} not found", page_id))?;
+        Ok(page)
+    }
+
+    fn add_note(&self, book_name: &str, content: &str) -> Result<()> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()?;
+        let book_uuid = Self::get_or_create_book_uuid(&tx, book_name)?;
+        tx.execute(
+            "INSERT INTO notes (uuid, book_uuid, body, added_on, edited_on, public, usn, dirty, deleted)
+             VALUES (?1, ?2, ?3, ?4, 0, false, 0, true, false)",
+            params![uuid::Uuid::new_v4().to_string(), book_uuid, content, now_nanos()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn edit_note(&self, page_id: NoteId, content: &str) -> Result<()> {
+        let conn = self.lock()?;
+        let changed = conn.execute(
+            "UPDATE notes SET body = ?1, edited_on = ?2, dirty = true
+             WHERE rowid = ?3 AND deleted = false",
+            params![content, now_nanos(), page_id],
+        )?;
+        Self::ensure_updated(changed, page_id)
+    }
+
+    fn move_note(&self, page_id: NoteId, book_name: &str) -> Result<()> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()?;
+        let book_uuid = Self::get_or_create_book_uuid(&tx, book_name)?;
+        let changed = tx.execute(
+            "UPDATE notes SET book_uuid = ?1, edited_on = ?2, dirty = true
+             WHERE rowid = ?3 AND deleted = false",
+            params![book_uuid, now_nanos(), page_id],
+        )?;
+        Self::ensure_updated(changed, page_id)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_note(&self, page_id: NoteId) -> Result<()> {
+        let conn = self.lock()?;
+        let changed = conn.execute(
+            "UPDATE notes SET deleted = true, dirty = true, body = ''
+             WHERE rowid = ?1 AND deleted = false",
+            params![page_id],
+        )?;
+        Self::ensure_updated(changed, page_id)
+    }
+
+    fn rename_book(&self, book_name: &str, new_name: &str) -> Result<()> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()?;
+        let book_uuid = Self::find_book_uuid(&tx, book_name)?
+            .ok_or_else(|| eyre!("Book {} not found", book_name))?;
+        if Self::find_book_uuid(&tx, new_name)?.is_some() {
+            return Err(eyre!("Book {} already exists", new_name));
+        }
+        tx.execute(
+            "UPDATE books SET label = ?1, dirty = true WHERE uuid = ?2",
+            params![new_name, book_uuid],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_book(&self, book_name: &str) -> Result<()> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()?;
+        let book_uuid = Self::find_book_uuid(&tx, book_name)?
+            .ok_or_else(|| eyre!("Book {} not found", book_name))?;
+        tx.execute(
+            "UPDATE notes SET deleted = true, dirty = true, body = ''
+             WHERE book_uuid = ?1 AND deleted = false",
+            params![book_uuid],
+        )?;
+        tx.execute(
+            "UPDATE books SET deleted = true, dirty = true, label = '' WHERE uuid = ?1",
+            params![book_uuid],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        // LIKE only ignores the case of ASCII letters, hits are confirmed by the snippet below
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare(
+            "SELECT notes.rowid, books.label, notes.body FROM notes
+             INNER JOIN books ON books.uuid = notes.book_uuid
+             WHERE notes.body LIKE ?1 ESCAPE '\\'
+               AND books.deleted = false AND notes.deleted = false
+             ORDER BY books.label ASC, notes.added_on ASC
+             LIMIT ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![pattern, search::MAX_RESULTS], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let hits = rows
+            .into_iter()
+            .filter_map(|(id, book_name, body)| {
+                let snippet = search::snippet(&body, query)?;
+                Some(SearchHit {
+                    book_name,
+                    id,
+                    snippet,
+                })
+            })
+            .collect();
+        Ok(hits)
+    }
+
+    fn sync(&self) -> Result<()> {
+        let dnote = self
+            .sync_with
+            .as_ref()
+            .ok_or_else(|| eyre!("Syncing is not supported by this backend"))?;
+        // Hold the lock so our own writes can't interleave with the CLI's
+        let _conn = self.lock()?;
+        dnote.sync()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// The tables of the dnote CLI's schema that the store touches.
+    const SCHEMA: &str = "
+        CREATE TABLE books (
+            uuid text PRIMARY KEY,
+            label text NOT NULL,
+            dirty bool DEFAULT false,
+            usn int DEFAULT 0 NOT NULL,
+            deleted bool DEFAULT false
+        );
+        CREATE TABLE notes (
+            uuid text NOT NULL,
+            book_uuid text NOT NULL,
+            body text NOT NULL,
+            added_on integer NOT NULL,
+            edited_on integer DEFAULT 0,
+            public bool DEFAULT false,
+            dirty bool DEFAULT false,
+            usn int DEFAULT 0 NOT NULL,
+            deleted bool DEFAULT false
+        );
+        INSERT INTO books (uuid, label, usn) VALUES ('b1', 'rust', 5), ('b2', 'docker', 6);
+        INSERT INTO notes (uuid, book_uuid, body, added_on, usn) VALUES
+            ('n1', 'b1', '# Ownership\n\nborrowing rules', 1, 7),
+            ('n2', 'b2', 'compose up', 2, 8),
+            ('n3', 'b1', 'cargo clippy', 3, 9);
+    ";
+
+    fn fixture() -> SqliteStore {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        SqliteStore::from_connection(conn)
+    }
+
+    fn query_note(store: &SqliteStore, rowid: NoteId) -> (String, bool, bool, i64, i64) {
+        store
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT body, dirty, deleted, usn, edited_on FROM notes WHERE rowid = ?1",
+                params![rowid],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn default_db_path_prefers_configured_env() {
+        let mut dnote = DnoteConfig::default();
+        dnote
+            .env
+            .insert("XDG_DATA_HOME".into(), "/tmp/work-notes".into());
+        assert_eq!(
+            default_db_path(&dnote),
+            Some(PathBuf::from("/tmp/work-notes/dnote/dnote.db"))
+        );
+        dnote.env.insert("DNOTE_DIR".into(), "/tmp/legacy".into());
+        assert_eq!(
+            default_db_path(&dnote),
+            Some(PathBuf::from("/tmp/legacy/dnote.db"))
+        );
+    }
+
+    #[test]
+    fn sqlite_store_reads_books_pages_and_content() {
+        let store = fixture();
+        let books = store.get_books().unwrap();
+        assert_eq!(
+            books.iter().map(|b| b.name.as_str()).collect::<Vec<_>>(),
+            vec!["docker", "rust"]
+        );
+        assert_eq!(books[1].note_count, Some(2));
+        assert_eq!(books[1].last_edited, from_dnote_timestamp(3));
+        let pages = store.get_pages("rust").unwrap();
+        assert_eq!(pages.iter().map(|p| p.id).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(pages[0].summary, "# Ownership");
+        assert_eq!(store.get_page_content(3).unwrap().content, "cargo clippy");
+        assert!(store.get_page_content(42).is_err());
+        store.remove_note(3).unwrap();
+        store.add_note("go", "gofmt").unwrap();
+        let books = store.get_books().unwrap();
+        assert_eq!(books[1].name, "go");
+        assert_eq!(books[2].note_count, Some(1));
+        assert_eq!(books[2].last_edited, from_dnote_timestamp(1));
+    }
+
+    #[test]
+    fn sqlite_store_searches_note_bodies() {
+        let store = fixture();
+        let hits = store.search("BORROW").unwrap();
+        assert_eq!(
+            hits,
+            vec![SearchHit {
+                book_name: "rust".into(),
+                id: 1,
+                snippet: "borrowing rules".into(),
+            }]
+        );
+        let ids: Vec<_> = store.search("o").unwrap().iter().map(|h| h.id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+        // LIKE wildcards are matched literally
+        assert!(store.search("%").unwrap().is_empty());
+        store.remove_note(1).unwrap();
+        assert!(store.search("borrow").unwrap().is_empty());
+    }
+
+    #[test]
+    fn sqlite_store_reads_note_metadata() {
+        let store = fixture();
+        let page = store.get_page(2).unwrap();
+        assert_eq!(page.uuid.as_deref(), Some("n2"));
+        assert_eq!(page.book_name.as_deref(), Some("docker"));
+        assert_eq!(page.added_on, from_dnote_timestamp(2));
+        assert_eq!(page.edited_on, None);
+        assert_eq!(page.public, Some(false));
+        assert!(store.get_page(42).is_err());
+    }
+
+    #[test]
+    fn sqlite_store_edit_marks_note_dirty_and_keeps_usn() {
+        let store = fixture();
+        store.edit_note(2, "compose down").unwrap();
+        let (body, dirty, deleted, usn, edited_on) = query_note(&store, 2);
+        assert_eq!(body, "compose down");
+        assert!(dirty);
+        assert!(!deleted);
+        assert_eq!(usn, 8);
+        assert!(edited_on > 0);
+        assert!(store.edit_note(42, "missing").is_err());
+    }
+
+    #[test]
+    fn sqlite_store_add_and_move_create_dirty_books() {
+        let store = fixture();
+        store.add_note("ops", "runbook").unwrap();
+        let pages = store.get_pages("ops").unwrap();
+        assert_eq!(pages.len(), 1);
+        let (_, dirty, _, usn, _) = query_note(&store, pages[0].id);
+        assert!(dirty);
+        assert_eq!(usn, 0);
+
+        store.move_note(2, "ops").unwrap();
+        assert!(store.get_pages("docker").unwrap().is_empty());
+        assert_eq!(store.get_pages("ops").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn sqlite_store_remove_is_a_soft_delete() {
+        let store = fixture();
+        store.remove_note(1).unwrap();
+        let (body, dirty, deleted, _, _) = query_note(&store, 1);
+        assert_eq!((body.as_str(), dirty, deleted), ("", true, true));
+        assert_eq!(store.get_pages("rust").unwrap().len(), 1);
+
+        store.remove_book("rust").unwrap();
+        assert!(query_note(&store, 3).2);
+        assert_eq!(store.get_books().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn sqlite_store_renames_books() {
+        let store = fixture();
+        store.rename_book("rust", "rustlang").unwrap();
+        assert_eq!(store.get_pages("rustlang").unwrap().len(), 2);
+        assert!(store.rename_book("docker", "rustlang").is_err());
+        assert!(store.rename_book("missing", "other").is_err());
+    }
+}
@@ -0,0 +1,467 @@
+//! Local trash keeping a copy of every deleted note and book, so they can be restored.
+//!
+//! dnote deletes for good, so the content and metadata of what is about to be deleted is written
+//! to a JSON file per delete in the trash directory first.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{action::Action, dnote::NoteId, store::NoteStore};
+
+/// A deleted note, as it was when it was deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TrashedNote {
+    pub id: NoteId,
+    pub book: String,
+    pub content: String,
+    pub added_on: Option<DateTime<Utc>>,
+    pub edited_on: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Trashed {
+    Note(TrashedNote),
+    Book {
+        name: String,
+        notes: Vec<TrashedNote>,
+    },
+}
+
+/// Something that was deleted, see [`Trash`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    pub deleted_at: DateTime<Utc>,
+    pub item: Trashed,
+}
+
+impl TrashEntry {
+    /// The book the entry is restored into.
+    pub fn book(&self) -> &str {
+        match &self.item {
+            Trashed::Note(note) => &note.book,
+            Trashed::Book { name, .. } => name,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.item {
+            Trashed::Note(note) => format!("note {} in {}", note.id, note.book),
+            Trashed::Book { name, notes } => match notes.len() {
+                1 => format!("book {name} with 1 note"),
+                n => format!("book {name} with {n} notes"),
+            },
+        }
+    }
+}
+
+/// Trash kept in a directory, usually `trash` in the app's data dir.
+#[derive(Debug, Clone, Default)]
+pub struct Trash {
+    dir: PathBuf,
+}
+
+impl Trash {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    /// Keep a copy of `item`.
+    pub fn put(&self, item: Trashed) -> Result<TrashEntry> {
+        let entry = TrashEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            deleted_at: Utc::now(),
+            item,
+        };
+        self.write(&entry)?;
+        Ok(entry)
+    }
+
+    fn write(&self, entry: &TrashEntry) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write then rename, so a crash never leaves half an entry behind
+        let tmp = self.dir.join(format!(".{}.tmp", entry.id));
+        fs::write(&tmp, serde_json::to_vec_pretty(entry)?)?;
+        fs::rename(&tmp, self.path(&entry.id))?;
+        Ok(())
+    }
+
+    /// Every entry, most recently deleted first. Unreadable entries are skipped.
+    pub fn list(&self) -> Result<Vec<TrashEntry>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut entries = Vec::new();
+        for file in dir {
+            let path = file?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match read_entry(&path) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => log::warn!("Skipping unreadable trash entry {}: {err}", path.display()),
+            }
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+        Ok(entries)
+    }
+
+    pub fn get(&self, id: &str) -> Result<TrashEntry> {
+        read_entry(&self.path(id)).map_err(|_| eyre!("Trash entry {id} not found"))
+    }
+
+    /// Delete entry `id` for good.
+    pub fn purge(&self, id: &str) -> Result<()> {
+        fs::remove_file(self.path(id)).map_err(|_| eyre!("Trash entry {id} not found"))
+    }
+
+    /// Delete the entries deleted more than `retention_days` before `now`, keeping everything
+    /// when it is 0. Returns how many were deleted.
+    pub fn purge_expired(&self, retention_days: u32, now: DateTime<Utc>) -> Result<usize> {
+        if retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = now - Duration::days(retention_days.into());
+        let expired: Vec<TrashEntry> = self
+            .list()?
+            .into_iter()
+            .filter(|entry| entry.deleted_at < cutoff)
+            .collect();
+        for entry in &expired {
+            self.purge(&entry.id)?;
+        }
+        Ok(expired.len())
+    }
+
+    /// Keep a copy of what `action` is about to delete from `store`, if it deletes anything.
+    /// Notes deleted in bulk get an entry each. Returns the entries added.
+    pub fn snapshot(&self, store: &dyn NoteStore, action: &Action) -> Result<Vec<TrashEntry>> {
+        let mut entries = Vec::new();
+        match action {
+            Action::DeleteNote(id) => {
+                entries.push(self.put(Trashed::Note(trashed_note(store, *id)?))?);
+            }
+            Action::DeleteNotes(ids) => {
+                let notes = ids
+                    .iter()
+                    .map(|id| trashed_note(store, *id))
+                    .collect::<Result<Vec<_>>>()?;
+                for note in notes {
+                    entries.push(self.put(Trashed::Note(note))?);
+                }
+            }
+            Action::DeleteBook(name) => {
+                let mut notes = Vec::new();
+                for page in store.get_pages(name)? {
+                    notes.push(TrashedNote {
+                        id: page.id,
+                        book: name.clone(),
+                        content: store.get_page_content(page.id)?.content,
+                        added_on: page.added_on,
+                        edited_on: page.edited_on,
+                    });
+                }
+                entries.push(self.put(Trashed::Book {
+                    name: name.clone(),
+                    notes,
+                })?);
+            }
+            _ => {}
+        }
+        Ok(entries)
+    }
+
+    /// Drop the `entries` of a delete that failed whose note or book is still in `store`, so
+    /// only what was really deleted stays in the trash.
+    pub fn forget_undeleted(&self, store: &dyn NoteStore, entries: &[TrashEntry]) -> Result<()> {
+        let books = store.get_books()?;
+        for entry in entries {
+            let kept = match &entry.item {
+                Trashed::Note(note) => store.get_page(note.id).is_ok(),
+                Trashed::Book { name, .. } => books.iter().any(|book| &book.name == name),
+            };
+            if kept {
+                self.purge(&entry.id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the notes of entry `id` back to their book, which is created again if it is gone,
+    /// then drop the entry. Restored notes get new ids.
+    ///
+    /// Each note is dropped from the entry once it is added, so retrying a restore that failed
+    /// halfway through a book doesn't add the first notes twice.
+    pub fn restore(&self, store: &dyn NoteStore, id: &str) -> Result<Action> {
+        let entry = self.get(id)?;
+        match &entry.item {
+            Trashed::Note(note) => store.add_note(&note.book, &note.content)?,
+            // dnote creates books along with their first note, so an empty one can't come back
+            Trashed::Book { name, notes } if notes.is_empty() => {
+                return Err(eyre!(
+                    "Book {name} had no notes, dnote can't restore an empty book"
+                ));
+            }
+            Trashed::Book { name, notes } => {
+                for (i, note) in notes.iter().enumerate() {
+                    store.add_note(&note.book, &note.content)?;
+                    let rest = &notes[i + 1..];
+                    if !rest.is_empty() {
+                        self.write(&TrashEntry {
+                            item: Trashed::Book {
+                                name: name.clone(),
+                                notes: rest.to_vec(),
+                            },
+                            ..entry.clone()
+                        })?;
+                    }
+                }
+            }
+        }
+        self.purge(id)?;
+        Ok(Action::NotesChanged {
+            message: format!("Restored {}", entry.describe()),
+            book: Some(entry.book().to_string()),
+        })
+    }
+}
+
+fn trashed_note(store: &dyn NoteStore, id: NoteId) -> Result<TrashedNote> {
+    let page = store.get_page(id)?;
+    let book = page
+        .book_name
+        .ok_or_else(|| eyre!("Book of note {id} is unknown"))?;
+    Ok(TrashedNote {
+        id,
+        book,
+        content: store.get_page_content(id)?.content,
+        added_on: page.added_on,
+        edited_on: page.edited_on,
+    })
+}
+
+fn read_entry(path: &Path) -> Result<TrashEntry> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        dnote::{DnoteBook, DnotePage, DnotePageInfo},
+        store::memory::MemoryStore,
+    };
+
+    fn store() -> MemoryStore {
+        MemoryStore::with_notes([
+            ("rust", "cargo clippy"),
+            ("rust", "cargo doc"),
+            ("docker", "compose up"),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn trash_snapshots_and_restores_notes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let trash = Trash::new(dir.path().join("trash"));
+        let store = store();
+        assert_eq!(trash.list()?, vec![]);
+
+        trash.snapshot(&store, &Action::DeleteNote(2))?;
+        store.remove_note(2)?;
+        let entries = trash.list()?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].describe(), "note 2 in rust");
+
+        let restored = trash.restore(&store, &entries[0].id)?;
+        assert_eq!(
+            restored,
+            Action::NotesChanged {
+                message: "Restored note 2 in rust".into(),
+                book: Some("rust".into()),
+            }
+        );
+        let pages = store.get_pages("rust")?;
+        assert_eq!(pages.last().unwrap().summary, "cargo doc");
+        assert_eq!(trash.list()?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn trash_restores_deleted_books() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let trash = Trash::new(dir.path().to_path_buf());
+        let store = store();
+        trash.snapshot(&store, &Action::DeleteBook("rust".into()))?;
+        store.remove_book("rust")?;
+        assert_eq!(store.get_books()?.len(), 1);
+
+        let entry = trash.list()?.remove(0);
+        assert_eq!(entry.describe(), "book rust with 2 notes");
+        trash.restore(&store, &entry.id)?;
+        let contents: Vec<String> = store
+            .get_pages("rust")?
+            .iter()
+            .map(|page| page.summary.clone())
+            .collect();
+        assert_eq!(contents, vec!["cargo clippy", "cargo doc"]);
+        Ok(())
+    }
+
+    #[test]
+    fn trash_refuses_to_restore_empty_books() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let trash = Trash::new(dir.path().to_path_buf());
+        let store = store();
+        let entry = trash.put(Trashed::Book {
+            name: "MachineLearning".into(),
+            notes: Vec::new(),
+        })?;
+        let err = trash.restore(&store, &entry.id).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Book MachineLearning had no notes, dnote can't restore an empty book"
+        );
+        assert_eq!(store.get_books()?.len(), 2);
+        assert_eq!(trash.list()?, vec![entry]);
+        Ok(())
+    }
+
+    /// Fails every `add_note` after the first `adds` ones.
+    struct FailingAdds {
+        store: MemoryStore,
+        adds: AtomicUsize,
+    }
+
+    impl NoteStore for FailingAdds {
+        fn get_books(&self) -> Result<Vec<DnoteBook>> {
+            self.store.get_books()
+        }
+        fn get_pages(&self, book_name: &str) -> Result<Vec<DnotePage>> {
+            self.store.get_pages(book_name)
+        }
+        fn get_page_content(&self, page_id: NoteId) -> Result<DnotePageInfo> {
+            self.store.get_page_content(page_id)
+        }
+        fn get_page(&self, page_id: NoteId) -> Result<DnotePage> {
+            self.store.get_page(page_id)
+        }
+        fn add_note(&self, book_name: &str, content: &str) -> Result<()> {
+            if self
+                .adds
+                .fetch_update(SeqCst, SeqCst, |n| n.checked_sub(1))
+                .is_err()
+            {
+                return Err(eyre!("database is locked"));
+            }
+            self.store.add_note(book_name, content)
+        }
+        fn edit_note(&self, page_id: NoteId, content: &str) -> Result<()> {
+            self.store.edit_note(page_id, content)
+        }
+        fn move_note(&self, page_id: NoteId, book_name: &str) -> Result<()> {
+            self.store.move_note(page_id, book_name)
+        }
+        fn remove_note(&self, page_id: NoteId) -> Result<()> {
+            self.store.remove_note(page_id)
+        }
+        fn rename_book(&self, book_name: &str, new_name: &str) -> Result<()> {
+            self.store.rename_book(book_name, new_name)
+        }
+        fn remove_book(&self, book_name: &str) -> Result<()> {
+            self.store.remove_book(book_name)
+        }
+    }
+
+    #[test]
+    fn trash_restores_the_rest_of_a_book_after_a_failed_restore() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let trash = Trash::new(dir.path().to_path_buf());
+        let store = FailingAdds {
+            store: MemoryStore::with_notes([
+                ("rust", "cargo clippy"),
+                ("rust", "cargo doc"),
+                ("rust", "cargo fmt"),
+            ])?,
+            adds: AtomicUsize::new(1),
+        };
+        let entry = trash
+            .snapshot(&store, &Action::DeleteBook("rust".into()))?
+            .remove(0);
+        store.remove_book("rust")?;
+
+        assert!(trash.restore(&store, &entry.id).is_err());
+        assert_eq!(trash.get(&entry.id)?.describe(), "book rust with 2 notes");
+
+        store.adds.store(usize::MAX, SeqCst);
+        trash.restore(&store, &entry.id)?;
+        let contents: Vec<String> = store
+            .get_pages("rust")?
+            .iter()
+            .map(|page| page.summary.clone())
+            .collect();
+        assert_eq!(contents, vec!["cargo clippy", "cargo doc", "cargo fmt"]);
+        assert_eq!(trash.list()?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn trash_purges_expired_entries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let trash = Trash::new(dir.path().to_path_buf());
+        trash.snapshot(&store(), &Action::DeleteNote(1))?;
+        let now = Utc::now();
+        assert_eq!(trash.purge_expired(30, now)?, 0);
+        assert_eq!(trash.purge_expired(0, now + Duration::days(60))?, 0);
+        assert_eq!(trash.purge_expired(30, now + Duration::days(31))?, 1);
+        assert_eq!(trash.list()?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn trash_keeps_an_entry_per_note_deleted_in_bulk() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let trash = Trash::new(dir.path().to_path_buf());
+        trash.snapshot(&store(), &Action::DeleteNotes(vec![1, 3]))?;
+        let mut described: Vec<String> = trash.list()?.iter().map(|e| e.describe()).collect();
+        described.sort();
+        assert_eq!(described, vec!["note 1 in rust", "note 3 in docker"]);
+        Ok(())
+    }
+
+    #[test]
+    fn trash_forgets_notes_a_failed_delete_left_in_place() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let trash = Trash::new(dir.path().to_path_buf());
+        let store = store();
+        let entries = trash.snapshot(&store, &Action::DeleteNotes(vec![1, 3]))?;
+        assert_eq!(entries.len(), 2);
+        store.remove_note(1)?;
+        trash.forget_undeleted(&store, &entries)?;
+        let described: Vec<String> = trash.list()?.iter().map(|e| e.describe()).collect();
+        assert_eq!(described, vec!["note 1 in rust"]);
+
+        let entries = trash.snapshot(&store, &Action::DeleteBook("docker".into()))?;
+        trash.forget_undeleted(&store, &entries)?;
+        assert_eq!(trash.list()?.len(), 1);
+        Ok(())
+    }
+}
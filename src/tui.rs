@@ -14,7 +14,11 @@
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
 use futures::{FutureExt, StreamExt};
-use ratatui::backend::CrosstermBackend as Backend;
+use ratatui::{
+    backend::CrosstermBackend as Backend,
+    layout::{Rect, Size},
+    Frame, Terminal,
+};
 use serde::{Deserialize, Serialize};
 use tokio::{
     sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
@@ -231,3 +235,71 @@ fn drop(&mut self) {
         self.exit().unwrap();
     }
 }
+
+/// A screen the app draws to, which it can hand over to another program (e.g. an editor) for a
+/// while. Implemented by [`Tui`] and by any ratatui [`Terminal`], e.g. one with a `TestBackend`.
+pub trait Screen {
+    fn size(&self) -> Result<Size>;
+    fn draw_frame(&mut self, render: &mut dyn FnMut(&mut Frame<'_>)) -> Result<()>;
+    fn resize(&mut self, area: Rect) -> Result<()>;
+    fn clear(&mut self) -> Result<()>;
+    /// Give the terminal back to the shell, so that another program can use it.
+    fn release(&mut self) -> Result<()>;
+    /// Take the terminal back after [`Screen::release`].
+    fn restore(&mut self) -> Result<()>;
+}
+
+impl Screen for Tui {
+    fn size(&self) -> Result<Size> {
+        Ok(self.terminal.size()?)
+    }
+
+    fn draw_frame(&mut self, render: &mut dyn FnMut(&mut Frame<'_>)) -> Result<()> {
+        self.terminal.draw(|f| render(f))?;
+        Ok(())
+    }
+
+    fn resize(&mut self, area: Rect) -> Result<()> {
+        Ok(self.terminal.resize(area)?)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        Ok(self.terminal.clear()?)
+    }
+
+    fn release(&mut self) -> Result<()> {
+        self.exit()
+    }
+
+    fn restore(&mut self) -> Result<()> {
+        self.enter()?;
+        Ok(self.terminal.clear()?)
+    }
+}
+
+impl<B: ratatui::backend::Backend> Screen for Terminal<B> {
+    fn size(&self) -> Result<Size> {
+        Ok(Terminal::size(self)?)
+    }
+
+    fn draw_frame(&mut self, render: &mut dyn FnMut(&mut Frame<'_>)) -> Result<()> {
+        Terminal::draw(self, |f| render(f))?;
+        Ok(())
+    }
+
+    fn resize(&mut self, area: Rect) -> Result<()> {
+        Ok(Terminal::resize(self, area)?)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        Ok(Terminal::clear(self)?)
+    }
+
+    fn release(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
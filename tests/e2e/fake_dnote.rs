@@ -0,0 +1,248 @@
+//! A scripted stand-in for the `dnote` CLI, backed by a small JSON store.
+//!
+//! The test executable runs as the fake when it is invoked as `dnote` (through a link on
+//! `PATH`). It reads and writes `store.json` in `$FAKE_DNOTE_DIR` and appends every command line
+//! it receives to `commands.jsonl` there, so that tests can assert on both.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use serde::{Deserialize, Serialize};
+
+pub const DIR_ENV: &str = "FAKE_DNOTE_DIR";
+const STORE_FILE: &str = "store.json";
+const COMMANDS_FILE: &str = "commands.jsonl";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FakeNote {
+    pub id: u32,
+    pub book: String,
+    pub content: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FakeStore {
+    pub notes: Vec<FakeNote>,
+    /// When set, `dnote sync` fails with this output.
+    #[serde(default)]
+    pub sync_error: Option<String>,
+    /// When set, `dnote add` fails with this output.
+    #[serde(default)]
+    pub add_error: Option<String>,
+}
+
+impl FakeStore {
+    pub fn with_notes(notes: &[(&str, &str)]) -> Self {
+        let notes = notes
+            .iter()
+            .zip(1..)
+            .map(|((book, content), id)| FakeNote {
+                id,
+                book: book.to_string(),
+                content: content.to_string(),
+            })
+            .collect();
+        Self {
+            notes,
+            ..Default::default()
+        }
+    }
+
+    pub fn load(dir: &Path) -> Self {
+        let json = fs::read_to_string(dir.join(STORE_FILE)).expect("fake dnote store");
+        serde_json::from_str(&json).expect("valid fake dnote store")
+    }
+
+    pub fn save(&self, dir: &Path) {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        fs::write(dir.join(STORE_FILE), json).unwrap();
+    }
+
+    pub fn books(&self) -> Vec<String> {
+        let mut books: Vec<_> = self.notes.iter().map(|n| n.book.clone()).collect();
+        books.sort();
+        books.dedup();
+        books
+    }
+
+    fn note(&mut self, id: &str) -> Result<&mut FakeNote, String> {
+        let id: u32 = id.parse().map_err(|_| format!("invalid note id {id}"))?;
+        self.notes
+            .iter_mut()
+            .find(|n| n.id == id)
+            .ok_or_else(|| format!("note {id} not found"))
+    }
+
+    fn has_book(&self, book: &str) -> bool {
+        self.notes.iter().any(|n| n.book == book)
+    }
+}
+
+/// Commands the fake received, one argument list per invocation.
+pub fn commands(dir: &Path) -> Vec<Vec<String>> {
+    fs::read_to_string(dir.join(COMMANDS_FILE))
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn summary(content: &str) -> (&str, bool) {
+    let mut lines = content.lines();
+    let first = lines.next().unwrap_or_default();
+    (first, lines.next().is_some())
+}
+
+/// Flag value following `flag`, e.g. the content in `add rust -c <content>`.
+fn flag<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+fn run(store: &mut FakeStore, args: &[String]) -> Result<String, String> {
+    let arg = |i: usize| args.get(i).map(String::as_str).unwrap_or_default();
+    match arg(0) {
+        "view" if arg(1).is_empty() => Ok(store
+            .books()
+            .iter()
+            .map(|b| {
+                let count = store.notes.iter().filter(|n| n.book == *b).count();
+                format!("  • {b} ({count})\n")
+            })
+            .collect()),
+        "view" if arg(1) == "--name-only" => {
+            Ok(store.books().iter().map(|b| format!("{b}\n")).collect())
+        }
+        "view" if arg(1).parse::<u32>().is_ok() => {
+            let note = store.note(arg(1))?.clone();
+            if arg(2) == "--content-only" {
+                return Ok(format!("{}\n", note.content));
+            }
+            Ok(format!(
+                "  • book name: {}\n  • created at: Jan 2, 2024 3:04pm (UTC)\n  • note id: {}\n  • note uuid: fake-{}\n\n------------------------content------------------------\n{}\n-------------------------------------------------------\n",
+                note.book, note.id, note.id, note.content
+            ))
+        }
+        "view" => {
+            let book = arg(1);
+            if !store.has_book(book) {
+                return Err(format!("book {book} not found"));
+            }
+            let mut out = format!("  • on book {book}\n");
+            for note in store.notes.iter().filter(|n| n.book == book) {
+                let (first, more) = summary(&note.content);
+                let marker = if more { " [---More---]" } else { "" };
+                out.push_str(&format!("({}) {}{}\n", note.id, first, marker));
+            }
+            Ok(out)
+        }
+        "add" => {
+            if let Some(error) = &store.add_error {
+                return Err(error.clone());
+            }
+            let content = flag(args, "-c").ok_or("content is required")?;
+            let id = store.notes.iter().map(|n| n.id).max().unwrap_or(0) + 1;
+            store.notes.push(FakeNote {
+                id,
+                book: arg(1).to_string(),
+                content: content.to_string(),
+            });
+            Ok(format!("  • added to {}\n", arg(1)))
+        }
+        "edit" if arg(1).parse::<u32>().is_ok() => {
+            let note = store.note(arg(1))?;
+            if let Some(content) = flag(args, "-c") {
+                note.content = content.to_string();
+            }
+            if let Some(book) = flag(args, "-b") {
+                note.book = book.to_string();
+            }
+            Ok("  • edited the note\n".to_string())
+        }
+        "edit" => {
+            let (book, new_name) = (arg(1), flag(args, "-n").ok_or("name is required")?);
+            if !store.has_book(book) {
+                return Err(format!("book {book} not found"));
+            }
+            for note in store.notes.iter_mut().filter(|n| n.book == book) {
+                note.book = new_name.to_string();
+            }
+            Ok("  • edited the book\n".to_string())
+        }
+        "rm" if arg(1).parse::<u32>().is_ok() => {
+            let id = store.note(arg(1))?.id;
+            store.notes.retain(|n| n.id != id);
+            Ok("  • removed the note\n".to_string())
+        }
+        "rm" => {
+            let book = arg(1);
+            if !store.has_book(book) {
+                return Err(format!("book {book} not found"));
+            }
+            store.notes.retain(|n| n.book != book);
+            Ok("  • removed the book\n".to_string())
+        }
+        "find" => {
+            let query = arg(1).to_lowercase();
+            let mut out = String::new();
+            for note in &store.notes {
+                let line = note
+                    .content
+                    .lines()
+                    .find(|l| l.to_lowercase().contains(&query));
+                if let Some(line) = line {
+                    out.push_str(&format!("({}) ({}) {}\n", note.id, note.book, line.trim()));
+                }
+            }
+            Ok(out)
+        }
+        "sync" => match &store.sync_error {
+            Some(error) => Err(error.clone()),
+            None => Ok("  • sync succeeded\n".to_string()),
+        },
+        command => Err(format!("unknown command {command:?}")),
+    }
+}
+
+/// Entry point when the test executable runs as `dnote`.
+pub fn main() -> ExitCode {
+    let dir = PathBuf::from(std::env::var(DIR_ENV).expect("FAKE_DNOTE_DIR is set"));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(COMMANDS_FILE))
+        .unwrap();
+    writeln!(log, "{}", serde_json::to_string(&args).unwrap()).unwrap();
+
+    let mut store = FakeStore::load(&dir);
+    match run(&mut store, &args) {
+        Ok(output) => {
+            store.save(&dir);
+            print!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Entry point when the test executable runs as the editor: `fake-editor <script> <file>`
+/// replaces the file being edited with the content of the script.
+pub fn editor_main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [script, file] = args.as_slice() else {
+        eprintln!("usage: fake-editor <script> <file>");
+        return ExitCode::FAILURE;
+    };
+    fs::copy(script, file).unwrap();
+    ExitCode::SUCCESS
+}
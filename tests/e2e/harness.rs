@@ -0,0 +1,272 @@
+//! Drives an [`App`] on a ratatui `TestBackend` against the fake `dnote`.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use dnote_tui::{
+    app::App,
+    config::{AppConfig, Config},
+    dnote::{DnoteBook, DnotePage, DnotePageInfo, NoteId},
+    search::SearchHit,
+    store::{self, NoteStore, SharedStore},
+    tui::Event,
+};
+use ratatui::{backend::TestBackend, layout::Size, Terminal};
+use tempfile::TempDir;
+
+use crate::fake_dnote::{self, FakeStore};
+
+const WIDTH: u16 = 120;
+const HEIGHT: u16 = 30;
+
+/// How long the app has to stay idle before a step is considered done.
+const QUIET_PERIOD: Duration = Duration::from_millis(30);
+const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Counts the store calls in flight, so that steps can wait for background loads.
+struct Tracked {
+    inner: SharedStore,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Tracked {
+    fn call<T>(&self, f: impl FnOnce(&dyn NoteStore) -> Result<T>) -> Result<T> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let result = f(self.inner.as_ref());
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+}
+
+impl NoteStore for Tracked {
+    fn get_books(&self) -> Result<Vec<DnoteBook>> {
+        self.call(|s| s.get_books())
+    }
+    fn get_pages(&self, book_name: &str) -> Result<Vec<DnotePage>> {
+        self.call(|s| s.get_pages(book_name))
+    }
+    fn get_page_content(&self, page_id: NoteId) -> Result<DnotePageInfo> {
+        self.call(|s| s.get_page_content(page_id))
+    }
+    fn get_page(&self, page_id: NoteId) -> Result<DnotePage> {
+        self.call(|s| s.get_page(page_id))
+    }
+    fn add_note(&self, book_name: &str, content: &str) -> Result<()> {
+        self.call(|s| s.add_note(book_name, content))
+    }
+    fn edit_note(&self, page_id: NoteId, content: &str) -> Result<()> {
+        self.call(|s| s.edit_note(page_id, content))
+    }
+    fn move_note(&self, page_id: NoteId, book_name: &str) -> Result<()> {
+        self.call(|s| s.move_note(page_id, book_name))
+    }
+    fn remove_note(&self, page_id: NoteId) -> Result<()> {
+        self.call(|s| s.remove_note(page_id))
+    }
+    fn rename_book(&self, book_name: &str, new_name: &str) -> Result<()> {
+        self.call(|s| s.rename_book(book_name, new_name))
+    }
+    fn remove_book(&self, book_name: &str) -> Result<()> {
+        self.call(|s| s.remove_book(book_name))
+    }
+    fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
+        self.call(|s| s.search(query))
+    }
+    fn sync(&self) -> Result<()> {
+        self.call(|s| s.sync())
+    }
+    fn clear_cache(&self) -> Result<()> {
+        self.call(|s| s.clear_cache())
+    }
+}
+
+pub struct Harness {
+    pub app: App,
+    pub terminal: Terminal<TestBackend>,
+    dir: TempDir,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Harness {
+    /// Start the app against a fake dnote holding `notes` as `(book, content)` pairs.
+    pub fn start(notes: &[(&str, &str)]) -> Result<Self> {
+        Self::start_with(FakeStore::with_notes(notes))
+    }
+
+    pub fn start_with(store: FakeStore) -> Result<Self> {
+        Self::start_configured(store, |_| {})
+    }
+
+    /// Start the app against `store`, with `configure` applied on top of the test config.
+    pub fn start_configured(
+        store: FakeStore,
+        configure: impl FnOnce(&mut AppConfig),
+    ) -> Result<Self> {
+        let dir = tempfile::tempdir()?;
+        store.save(dir.path());
+
+        let mut config = Config::new()?;
+        config.config.dnote.bin = PathBuf::from("dnote");
+        config.config.dnote.env.insert(
+            fake_dnote::DIR_ENV.to_string(),
+            dir.path().display().to_string(),
+        );
+        let editor_script = dir.path().join("editor-script.md");
+        config.config.editor = Some(format!(
+            "fake-editor {}",
+            shlex::try_quote(&editor_script.display().to_string())?
+        ));
+        config.config.data_dir = dir.path().join("data");
+        configure(&mut config.config);
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let store = Arc::new(Tracked {
+            inner: store::open(&config.config)?,
+            in_flight: in_flight.clone(),
+        });
+
+        let mut app = App::new(4.0, 60.0, config, store)?;
+        let terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT))?;
+        app.init(Size::new(WIDTH, HEIGHT))?;
+        let mut harness = Self {
+            app,
+            terminal,
+            dir,
+            in_flight,
+        };
+        harness.settle()?;
+        Ok(harness)
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Set what the fake editor saves the next time a note is composed or edited.
+    pub fn editor_will_write(&self, content: &str) -> Result<()> {
+        std::fs::write(self.dir().join("editor-script.md"), content)?;
+        Ok(())
+    }
+
+    /// Process actions until the app and its background store calls go quiet.
+    pub fn settle(&mut self) -> Result<()> {
+        let started = Instant::now();
+        let mut quiet_since = Instant::now();
+        while quiet_since.elapsed() < QUIET_PERIOD {
+            if started.elapsed() > STEP_TIMEOUT {
+                return Err(eyre!("App did not settle within {:?}", STEP_TIMEOUT));
+            }
+            if !self.app.action_rx.is_empty() {
+                self.app.handle_actions(&mut self.terminal)?;
+                quiet_since = Instant::now();
+            } else if self.in_flight.load(Ordering::SeqCst) > 0 {
+                quiet_since = Instant::now();
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        Ok(())
+    }
+
+    pub fn key(&mut self, code: KeyCode) -> Result<()> {
+        self.key_with(code, KeyModifiers::NONE)
+    }
+
+    pub fn key_with(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        let event = Event::Key(KeyEvent::new(code, modifiers));
+        self.app.handle_event(event)?;
+        self.settle()
+    }
+
+    /// Press `c` while holding Ctrl.
+    pub fn ctrl(&mut self, c: char) -> Result<()> {
+        self.key_with(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    /// Type `text` into whatever has focus, with newlines as Enter.
+    pub fn type_text(&mut self, text: &str) -> Result<()> {
+        for c in text.chars() {
+            match c {
+                '\n' => self.key(KeyCode::Enter)?,
+                c => self.key(KeyCode::Char(c))?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Press each character of `keys` in turn.
+    pub fn press(&mut self, keys: &str) -> Result<()> {
+        for c in keys.chars() {
+            self.key(KeyCode::Char(c))?;
+        }
+        Ok(())
+    }
+
+    /// Send a mouse event at `column` and `row`, drawing first so it lands on what is shown.
+    pub fn mouse(&mut self, kind: MouseEventKind, column: u16, row: u16) -> Result<()> {
+        self.app.draw(&mut self.terminal)?;
+        let event = Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+        self.app.handle_event(event)?;
+        self.settle()
+    }
+
+    /// Send a mouse event of `kind` where `text` first shows on screen.
+    pub fn mouse_on(&mut self, kind: MouseEventKind, text: &str) -> Result<()> {
+        let screen = self.screen()?;
+        let (column, row) = screen
+            .lines()
+            .enumerate()
+            .find_map(|(row, line)| Some((line[..line.find(text)?].chars().count(), row)))
+            .ok_or_else(|| eyre!("{text:?} is not on screen:\n{screen}"))?;
+        self.mouse(kind, column as u16, row as u16)
+    }
+
+    /// Left click where `text` first shows on screen.
+    pub fn click_on(&mut self, text: &str) -> Result<()> {
+        self.mouse_on(MouseEventKind::Down(MouseButton::Left), text)
+    }
+
+    /// Render the app and return the screen, one line per row.
+    pub fn screen(&mut self) -> Result<String> {
+        self.app.handle_event(Event::Tick)?;
+        self.settle()?;
+        self.app.draw(&mut self.terminal)?;
+        let buffer = self.terminal.backend().buffer();
+        let lines: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
+    /// Commands the fake dnote received, e.g. `["view", "rust"]`.
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        fake_dnote::commands(self.dir())
+    }
+
+    /// Whether the fake dnote received `command`.
+    pub fn received(&self, command: &[&str]) -> bool {
+        self.commands().iter().any(|c| c == command)
+    }
+
+    pub fn store(&self) -> FakeStore {
+        FakeStore::load(self.dir())
+    }
+}
@@ -0,0 +1,861 @@
+//! End-to-end tests that drive the app against a fake `dnote` executable.
+//!
+//! This test binary doubles as the fake: it is linked as `dnote` and `fake-editor` into a
+//! directory put on `PATH`, and dispatches on the name it was invoked as. That needs a custom
+//! `main`, so the test runs without libtest (`harness = false`).
+
+mod fake_dnote;
+mod harness;
+
+use std::{env, path::Path, process::ExitCode};
+
+use color_eyre::eyre::{ensure, Result};
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEventKind};
+use dnote_tui::{action::Action, state::Mode};
+
+use crate::{fake_dnote::FakeStore, harness::Harness};
+
+fn browses_books_pages_and_content() -> Result<()> {
+    let mut h = Harness::start(&[
+        ("rust", "# Ownership\n\nborrowing rules"),
+        ("docker", "compose up"),
+        ("rust", "cargo clippy"),
+    ])?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("docker") && screen.contains("rust"), screen);
+
+    // Select `docker`, then `rust`, then focus its pages
+    h.press("jjl")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("# Ownership"), screen);
+    ensure!(screen.contains("cargo clippy"), screen);
+    ensure!(screen.contains("borrowing rules"), screen);
+    ensure!(h.received(&["view"]));
+    ensure!(h.received(&["view", "rust"]));
+    ensure!(h.received(&["view", "1", "--content-only"]));
+    Ok(())
+}
+
+fn adds_a_note_to_the_active_book() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy")])?;
+    h.press("jla")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("New note in rust"), screen);
+    ensure!(h.app.state.mode == Mode::Content);
+    h.type_text("# rustfmt\neverything\n")?;
+    h.ctrl('s')?;
+    ensure!(
+        h.received(&["add", "rust", "-c", "# rustfmt\neverything"]),
+        "{:?}",
+        h.commands()
+    );
+    ensure!(h.app.state.mode == Mode::Page);
+    let screen = h.screen()?;
+    ensure!(screen.contains("# rustfmt"), screen);
+    ensure!(screen.contains("Added note to rust"), screen);
+    Ok(())
+}
+
+fn shows_note_counts_and_totals_in_the_books_pane() -> Result<()> {
+    let mut h = Harness::start(&[
+        ("rust", "cargo clippy"),
+        ("docker", "compose up"),
+        ("rust", "cargo doc"),
+    ])?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("│docker        1│"), screen);
+    ensure!(screen.contains("│rust          2│"), screen);
+    ensure!(screen.contains("╰ 3 notes  0/2 ─╯"), screen);
+
+    // Counts follow changes to the books
+    h.press("jjla")?;
+    h.type_text("cargo fmt")?;
+    h.ctrl('s')?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("│rust          3│"), screen);
+    ensure!(screen.contains("╰ 4 notes  2/2 ─╯"), screen);
+    Ok(())
+}
+
+fn keeps_the_editor_open_when_saving_fails() -> Result<()> {
+    let mut store = FakeStore::with_notes(&[("rust", "cargo clippy")]);
+    store.add_error = Some("database is locked".into());
+    let mut h = Harness::start_with(store)?;
+    h.press("jla")?;
+    h.type_text("# rustfmt")?;
+    h.ctrl('s')?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("database is locked"), screen);
+    ensure!(screen.contains("# rustfmt"), screen);
+    ensure!(h.app.state.mode == Mode::Content);
+
+    // Saving again once dnote recovers keeps what was written
+    let mut store = h.store();
+    store.add_error = None;
+    store.save(h.dir());
+    h.type_text(" everything")?;
+    h.ctrl('s')?;
+    ensure!(
+        h.received(&["add", "rust", "-c", "# rustfmt everything"]),
+        "{:?}",
+        h.commands()
+    );
+    ensure!(h.app.state.mode == Mode::Page);
+    ensure!(h.screen()?.contains("# rustfmt everything"));
+    Ok(())
+}
+
+fn edits_the_active_note() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy")])?;
+    h.press("jle")?;
+    ensure!(h.screen()?.contains("Editing note 1"));
+    h.ctrl('e')?;
+    h.type_text(" --fix")?;
+    h.ctrl('s')?;
+    ensure!(
+        h.received(&["edit", "1", "-c", "cargo clippy --fix"]),
+        "{:?}",
+        h.commands()
+    );
+    ensure!(h.screen()?.contains("cargo clippy --fix"));
+    Ok(())
+}
+
+fn discards_unsaved_edits_on_double_escape() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy")])?;
+    h.press("jle")?;
+    h.type_text("x")?;
+    h.key(KeyCode::Esc)?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("press Esc again"), screen);
+    h.key(KeyCode::Esc)?;
+    ensure!(!h.screen()?.contains("Editing note 1"));
+    ensure!(!h.commands().iter().any(|c| c[0] == "edit"));
+    ensure!(h.app.state.mode == Mode::Page);
+    Ok(())
+}
+
+fn edits_the_active_note_externally() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy")])?;
+    h.editor_will_write("cargo clippy --fix\n")?;
+    h.press("jl")?;
+    h.key_with(KeyCode::Char('E'), KeyModifiers::SHIFT)?;
+    ensure!(
+        h.received(&["edit", "1", "-c", "cargo clippy --fix"]),
+        "{:?}",
+        h.commands()
+    );
+    Ok(())
+}
+
+fn creates_a_book_with_its_first_note() -> Result<()> {
+    let store = FakeStore::with_notes(&[("rust", "cargo clippy")]);
+    let mut h = Harness::start_configured(store, |config| config.external_editor = true)?;
+    h.editor_will_write("go fmt ./...")?;
+    h.press("a")?;
+    h.press("golang")?;
+    h.key(KeyCode::Enter)?;
+    ensure!(
+        h.received(&["add", "golang", "-c", "go fmt ./..."]),
+        "{:?}",
+        h.commands()
+    );
+    let screen = h.screen()?;
+    ensure!(
+        screen.contains("golang") && screen.contains("go fmt ./..."),
+        screen
+    );
+    Ok(())
+}
+
+fn renders_markdown_and_toggles_raw_view() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "intro\n\n**bold words** and `code`")])?;
+    h.press("jl")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("bold words and code"), screen);
+    ensure!(!screen.contains("**bold words**"), screen);
+    h.press("m")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("**bold words** and `code`"), screen);
+    ensure!(screen.contains(" raw "), screen);
+    Ok(())
+}
+
+fn focuses_and_scrolls_the_content_pane() -> Result<()> {
+    let rows: Vec<String> = (1..=60).map(|i| format!("row {i:02}")).collect();
+    let long = format!("# Long note\n\n{}", rows.join("\n"));
+    let mut h = Harness::start(&[("rust", &long), ("rust", "short")])?;
+    h.press("jll")?;
+    ensure!(h.app.state.mode == Mode::Content);
+    let screen = h.screen()?;
+    ensure!(
+        screen.contains("row 02") && !screen.contains("row 60"),
+        screen
+    );
+
+    h.key_with(KeyCode::Char('G'), KeyModifiers::SHIFT)?;
+    let screen = h.screen()?;
+    ensure!(
+        screen.contains("row 60") && !screen.contains("row 02"),
+        screen
+    );
+    ensure!(screen.contains("of 62"), screen);
+    h.press("gg")?;
+    ensure!(h.screen()?.contains("row 02"));
+    h.ctrl('d')?;
+    ensure!(!h.screen()?.contains("row 02"));
+
+    // Another note starts at the top again
+    h.press("h")?;
+    ensure!(h.app.state.mode == Mode::Page);
+    h.press("jkl")?;
+    ensure!(h.app.state.mode == Mode::Content);
+    ensure!(h.screen()?.contains("row 02"));
+    Ok(())
+}
+
+fn clicks_and_scrolls_with_the_mouse() -> Result<()> {
+    let rows: Vec<String> = (1..=60).map(|i| format!("row {i:02}")).collect();
+    let long = format!("# Long note\n\n{}", rows.join("\n"));
+    let mut h = Harness::start(&[
+        ("rust", "cargo clippy"),
+        ("rust", &long),
+        ("docker", "compose up"),
+    ])?;
+
+    // Clicking a book loads its pages, clicking a page loads its content
+    h.click_on("rust")?;
+    ensure!(h.app.state.mode == Mode::Book);
+    let screen = h.screen()?;
+    ensure!(screen.contains("[2] # Long note"), screen);
+    h.click_on("# Long note")?;
+    ensure!(h.app.state.mode == Mode::Page);
+    let screen = h.screen()?;
+    ensure!(screen.contains("row 02"), screen);
+
+    // The wheel scrolls the pane under the mouse, whichever is focused
+    for _ in 0..5 {
+        h.mouse_on(MouseEventKind::ScrollDown, "row 10")?;
+    }
+    let screen = h.screen()?;
+    ensure!(
+        !screen.contains("row 02") && screen.contains("row 10"),
+        screen
+    );
+    ensure!(h.app.state.mode == Mode::Page);
+    h.mouse_on(MouseEventKind::ScrollUp, "cargo clippy")?;
+    ensure!(h.screen()?.contains("│cargo clippy"));
+    h.click_on("Content")?;
+    ensure!(h.app.state.mode == Mode::Content);
+
+    // Clicking outside a popup cancels it, clicking inside keeps it
+    h.press("hhr")?;
+    ensure!(h.screen()?.contains("Rename Book"));
+    h.click_on("Rename Book")?;
+    ensure!(h.screen()?.contains("Rename Book"));
+    h.mouse(MouseEventKind::Down(MouseButton::Left), 0, 0)?;
+    let screen = h.screen()?;
+    ensure!(!screen.contains("Rename Book"), screen);
+    // Keys are back to the panes
+    h.press("k")?;
+    ensure!(h.app.state.books.state.selected() == Some(0));
+
+    // Cancelling the search by clicking also leaves search mode
+    h.press("f")?;
+    ensure!(h.app.state.mode == Mode::Search);
+    h.mouse(MouseEventKind::Down(MouseButton::Left), 0, 0)?;
+    let screen = h.screen()?;
+    ensure!(!screen.contains("> Search <"), screen);
+    ensure!(h.app.state.mode == Mode::Book);
+    h.press("j")?;
+    ensure!(h.app.state.books.state.selected() == Some(1));
+    Ok(())
+}
+
+fn moves_the_active_note_to_another_book() -> Result<()> {
+    let mut h = Harness::start(&[
+        ("docker", "compose up"),
+        ("rust", "cargo clippy"),
+        ("rust", "cargo doc"),
+    ])?;
+    h.press("jjl")?;
+    h.key_with(KeyCode::Char('M'), KeyModifiers::SHIFT)?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("Move Note 2 From rust"), screen);
+    h.press("dock")?;
+    h.key(KeyCode::Enter)?;
+    ensure!(
+        h.received(&["edit", "2", "-b", "docker"]),
+        "{:?}",
+        h.commands()
+    );
+    let screen = h.screen()?;
+    ensure!(!screen.contains("cargo clippy"), screen);
+    ensure!(screen.contains("Moved note 2 to docker"), screen);
+
+    // A new book can be typed in
+    h.key_with(KeyCode::Char('M'), KeyModifiers::SHIFT)?;
+    h.press("tools")?;
+    h.key(KeyCode::Down)?;
+    ensure!(h.screen()?.contains("(new book)"));
+    h.key(KeyCode::Enter)?;
+    ensure!(
+        h.received(&["edit", "3", "-b", "tools"]),
+        "{:?}",
+        h.commands()
+    );
+    ensure!(
+        h.store().books() == ["docker", "tools"],
+        "{:?}",
+        h.store().books()
+    );
+
+    h.press("hk")?;
+    ensure!(h.screen()?.contains("cargo clippy"));
+    Ok(())
+}
+
+fn exports_moves_and_deletes_marked_notes() -> Result<()> {
+    let mut h = Harness::start(&[
+        ("docker", "compose up"),
+        ("rust", "cargo clippy"),
+        ("rust", "cargo doc"),
+        ("rust", "cargo fmt"),
+    ])?;
+    h.press("jjl  ")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("2 marked"), screen);
+
+    h.press("x")?;
+    ensure!(h.screen()?.contains("Export 2 notes"));
+    let path = h.dir().join("export.md");
+    h.ctrl('u')?;
+    h.type_text(&path.display().to_string())?;
+    h.key(KeyCode::Enter)?;
+    ensure!(std::fs::read_to_string(&path)? == "cargo clippy\n\n---\n\ncargo doc\n");
+    ensure!(h.screen()?.contains("Exported 2 notes"));
+
+    h.key_with(KeyCode::Char('M'), KeyModifiers::SHIFT)?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("Move 2 Notes From rust"), screen);
+    h.press("dock")?;
+    h.key(KeyCode::Enter)?;
+    ensure!(h.received(&["edit", "2", "-b", "docker"]));
+    ensure!(h.received(&["edit", "3", "-b", "docker"]));
+    let screen = h.screen()?;
+    ensure!(screen.contains("Moved 2 notes to docker"), screen);
+    ensure!(!screen.contains("marked"), screen);
+
+    h.ctrl('a')?;
+    h.press("d")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("Delete 1 note?"), screen);
+    h.press("y")?;
+    ensure!(h.received(&["rm", "4", "-y"]), "{:?}", h.commands());
+    Ok(())
+}
+
+fn counts_the_notes_a_bulk_delete_does_not_list() -> Result<()> {
+    let notes: Vec<(&str, String)> = (1..=6).map(|i| ("rust", format!("note {i}"))).collect();
+    let notes: Vec<(&str, &str)> = notes.iter().map(|(b, c)| (*b, c.as_str())).collect();
+    let mut h = Harness::start(&notes)?;
+    h.press("jl")?;
+    h.ctrl('a')?;
+    h.press("d")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("Delete 6 notes?"), screen);
+    ensure!(screen.contains("…and 3 more"), screen);
+    ensure!(screen.contains("Press y to confirm"), screen);
+    h.press("y")?;
+    for id in 1..=6 {
+        let id = id.to_string();
+        ensure!(h.received(&["rm", &id, "-y"]), "{:?}", h.commands());
+    }
+    Ok(())
+}
+
+fn sorts_pages_keeping_the_selected_note() -> Result<()> {
+    let mut h = Harness::start(&[
+        ("rust", "cargo doc"),
+        ("rust", "borrowing"),
+        ("rust", "cargo clippy --fix"),
+    ])?;
+    h.press("jl")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("id ↑ [rust]"), screen);
+    let order = |screen: &str| {
+        let mut ids = [1, 2, 3].map(|id| (screen.find(&format!("[{id}]")).unwrap(), id));
+        ids.sort();
+        ids.map(|(_, id)| id)
+    };
+    ensure!(order(&screen) == [1, 2, 3], screen);
+
+    h.press("o")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("summary ↑ [rust]"), screen);
+    ensure!(order(&screen) == [2, 3, 1], screen);
+    ensure!(screen.contains("3 of 3"), screen);
+
+    h.key_with(KeyCode::Char('O'), KeyModifiers::SHIFT)?;
+    h.press("ooo")?;
+    h.key_with(KeyCode::Char('O'), KeyModifiers::SHIFT)?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("length ↓ [rust]"), screen);
+    ensure!(order(&screen) == [3, 2, 1], screen);
+    ensure!(screen.contains("3 of 3"), screen);
+    let saved = std::fs::read_to_string(h.dir().join("data/page_sorts.json"))?;
+    ensure!(saved.contains("Length"), saved);
+    Ok(())
+}
+
+fn filters_books_and_pages_as_you_type() -> Result<()> {
+    let mut h = Harness::start(&[
+        ("docker", "compose up"),
+        ("golang", "go vet"),
+        ("rust", "cargo clippy"),
+        ("rust", "cargo doc"),
+        ("rust", "rustup update"),
+    ])?;
+    h.press("jj/")?;
+    h.type_text("RU")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("/RU"), screen);
+    // The books pane is too narrow for the whole count
+    ensure!(screen.contains("filtered: 1 of"), screen);
+    ensure!(!screen.contains("docker"), screen);
+    ensure!(screen.contains("cargo clippy"), screen);
+    h.key(KeyCode::Esc)?;
+    let screen = h.screen()?;
+    ensure!(
+        screen.contains("docker") && !screen.contains("filtered"),
+        screen
+    );
+    ensure!(screen.contains("go vet"), screen);
+
+    h.press("jl/")?;
+    h.type_text("CARGO")?;
+    h.key(KeyCode::Enter)?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("filtered: 2 of 3"), screen);
+    ensure!(!screen.contains("rustup update"), screen);
+    h.press("n")?;
+    ensure!(h.screen()?.contains("2 of 2"));
+    h.press("n")?;
+    ensure!(h.screen()?.contains("1 of 2"));
+    h.key_with(KeyCode::Char('N'), KeyModifiers::SHIFT)?;
+    let screen = h.screen()?;
+    ensure!(
+        screen.contains("2 of 2") && screen.contains("│cargo doc"),
+        screen
+    );
+
+    h.press("/")?;
+    h.key(KeyCode::Esc)?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("rustup update"), screen);
+    ensure!(screen.contains("1 of 3"), screen);
+    Ok(())
+}
+
+fn moves_and_jumps_to_books_hidden_by_the_filter() -> Result<()> {
+    let mut h = Harness::start(&[("docker", "compose up"), ("rust", "cargo clippy")])?;
+    h.press("/")?;
+    h.type_text("RU")?;
+    h.key(KeyCode::Enter)?;
+    h.press("l")?;
+    h.key_with(KeyCode::Char('M'), KeyModifiers::SHIFT)?;
+    h.press("docker")?;
+    let screen = h.screen()?;
+    ensure!(!screen.contains("(new book)"), screen);
+    h.key(KeyCode::Esc)?;
+
+    h.press("f")?;
+    h.press("compose")?;
+    h.key(KeyCode::Enter)?;
+    let screen = h.screen()?;
+    ensure!(h.app.state.get_active_book().unwrap().name == "docker");
+    ensure!(h.app.state.get_active_page().unwrap().id == 1);
+    ensure!(h.app.state.books.filter_query().is_none());
+    ensure!(screen.contains("│compose up"), screen);
+    Ok(())
+}
+
+fn renames_the_active_book() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy")])?;
+    h.press("jr")?;
+    for _ in "rust".chars() {
+        h.key(KeyCode::Backspace)?;
+    }
+    h.press("rustlang")?;
+    h.key(KeyCode::Enter)?;
+    ensure!(
+        h.received(&["edit", "rust", "-n", "rustlang"]),
+        "{:?}",
+        h.commands()
+    );
+    let screen = h.screen()?;
+    ensure!(screen.contains("rustlang"), screen);
+    // The renamed book stays selected and its notes stay listed
+    ensure!(screen.contains("cargo clippy"), screen);
+    ensure!(h.store().books() == ["rustlang"]);
+    Ok(())
+}
+
+fn rejects_invalid_book_names() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy")])?;
+    h.press("jr")?;
+    h.press(" notes")?;
+    h.key(KeyCode::Enter)?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("Book name cannot contain spaces"), screen);
+    ensure!(!h.commands().iter().any(|c| c[0] == "edit"));
+    h.key(KeyCode::Esc)?;
+    ensure!(!h.screen()?.contains("Rename Book"));
+    Ok(())
+}
+
+fn deletes_the_active_note() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy"), ("rust", "cargo doc")])?;
+    h.press("jld")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("Delete note 1?"), screen);
+    ensure!(!h.commands().iter().any(|c| c[0] == "rm"));
+    h.press("y")?;
+    ensure!(h.received(&["rm", "1", "-y"]), "{:?}", h.commands());
+    let screen = h.screen()?;
+    ensure!(!screen.contains("cargo clippy"), screen);
+    ensure!(screen.contains("cargo doc"), screen);
+    Ok(())
+}
+
+fn cancels_deletes_unless_confirmed() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy")])?;
+    h.press("jd")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("Delete book rust and its note?"), screen);
+    h.press("n")?;
+    h.press("ld")?;
+    h.key(KeyCode::Esc)?;
+    let screen = h.screen()?;
+    ensure!(!screen.contains("Delete note"), screen);
+    ensure!(screen.contains("cargo clippy"), screen);
+    ensure!(!h.commands().iter().any(|c| c[0] == "rm"));
+    Ok(())
+}
+
+fn deletes_notes_without_confirmation_when_configured() -> Result<()> {
+    let store = FakeStore::with_notes(&[("rust", "cargo clippy"), ("rust", "cargo doc")]);
+    let mut h = Harness::start_configured(store, |config| config.confirm_note_deletion = false)?;
+    h.press("jld")?;
+    ensure!(h.received(&["rm", "1", "-y"]), "{:?}", h.commands());
+    Ok(())
+}
+
+fn restores_deleted_notes_from_the_trash() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy"), ("rust", "cargo doc")])?;
+    h.press("jldy")?;
+    ensure!(h.received(&["rm", "1", "-y"]), "{:?}", h.commands());
+    h.press("t")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("note 1 in rust"), screen);
+    ensure!(screen.contains("cargo clippy"), screen);
+    h.press("r")?;
+    ensure!(
+        h.received(&["add", "rust", "-c", "cargo clippy"]),
+        "{:?}",
+        h.commands()
+    );
+    let screen = h.screen()?;
+    ensure!(screen.contains("Restored note 1 in rust"), screen);
+    h.press("t")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("0 deleted"), screen);
+    Ok(())
+}
+
+fn asks_before_purging_from_the_trash() -> Result<()> {
+    let mut h = Harness::start(&[("rust", "cargo clippy"), ("rust", "cargo doc")])?;
+    h.press("jldyt")?;
+    h.press("d")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("Delete note 1 in rust for good?"), screen);
+    h.press("n")?;
+    h.press("t")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("1 deleted"), screen);
+    h.press("dy")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("> Trash <"), screen);
+    ensure!(screen.contains("0 deleted"), screen);
+    Ok(())
+}
+
+fn deletes_the_active_book() -> Result<()> {
+    let mut h = Harness::start(&[
+        ("docker", "compose up"),
+        ("rust", "cargo clippy"),
+        ("rust", "cargo doc"),
+    ])?;
+    h.press("jjd")?;
+    let screen = h.screen()?;
+    ensure!(
+        screen.contains("Delete book rust and all 2 of its notes?"),
+        screen
+    );
+    h.press("y")?;
+    ensure!(h.received(&["rm", "rust", "-y"]), "{:?}", h.commands());
+    let screen = h.screen()?;
+    ensure!(!screen.contains("cargo clippy"), screen);
+    ensure!(screen.contains("docker"), screen);
+    ensure!(h.store().books() == ["docker"]);
+    Ok(())
+}
+
+fn shows_sync_failures_in_a_popup() -> Result<()> {
+    let mut store = FakeStore::with_notes(&[("rust", "cargo clippy")]);
+    store.sync_error = Some("not logged in".into());
+    let mut h = Harness::start_with(store)?;
+    h.press("s")?;
+    ensure!(h.received(&["sync"]));
+    let screen = h.screen()?;
+    ensure!(screen.contains("Sync Failed"), screen);
+    ensure!(screen.contains("dnote login"), screen);
+    Ok(())
+}
+
+fn returns_from_search_when_another_popup_replaces_it() -> Result<()> {
+    let mut h = Harness::start(&[("docker", "compose up"), ("rust", "cargo clippy")])?;
+    h.press("jf")?;
+    ensure!(h.app.state.mode == Mode::Search);
+    // A sync started before the search fails while it is open
+    h.app
+        .action_tx
+        .send(Action::SyncFailed("not logged in".into()))?;
+    h.settle()?;
+    ensure!(h.screen()?.contains("Sync Failed"));
+    h.key(KeyCode::Esc)?;
+    let screen = h.screen()?;
+    ensure!(!screen.contains("Sync Failed"), screen);
+    ensure!(h.app.state.mode == Mode::Book);
+    h.press("j")?;
+    ensure!(h.app.state.books.state.selected() == Some(1));
+    Ok(())
+}
+
+fn searches_all_books_and_jumps_to_a_hit() -> Result<()> {
+    let mut h = Harness::start(&[
+        ("docker", "compose up"),
+        ("rust", "# Ownership\n\nborrowing rules"),
+        ("rust", "cargo clippy"),
+    ])?;
+    h.press("f")?;
+    h.press("borrow")?;
+    ensure!(h.received(&["find", "borrow"]), "{:?}", h.commands());
+    let screen = h.screen()?;
+    ensure!(screen.contains("[rust] 2 borrowing rules"), screen);
+    ensure!(screen.contains("1 match"), screen);
+    h.key(KeyCode::Enter)?;
+    let screen = h.screen()?;
+    ensure!(!screen.contains("> Search <"), screen);
+    // The hit's book and note are selected, and its content is shown
+    ensure!(h.app.state.get_active_book().unwrap().name == "rust");
+    ensure!(h.app.state.get_active_page().unwrap().id == 2);
+    ensure!(h.app.state.mode == Mode::Page);
+    ensure!(screen.contains("borrowing rules"), screen);
+    Ok(())
+}
+
+fn finds_and_previews_notes_fuzzily() -> Result<()> {
+    let mut h = Harness::start(&[
+        ("docker", "compose up"),
+        ("rust", "# Ownership\n\nborrowing rules"),
+        ("rust", "cargo clippy\n\nlints everything"),
+    ])?;
+    h.ctrl('p')?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("> Find <"), screen);
+    ensure!(screen.contains("5 / 5"), screen);
+    h.press("rs clpy")?;
+    let screen = h.screen()?;
+    ensure!(screen.contains("1 / 5"), screen);
+    ensure!(screen.contains("rust cargo clippy (3)"), screen);
+    // The selected note is previewed
+    ensure!(screen.contains("lints everything"), screen);
+    h.key(KeyCode::Enter)?;
+    let screen = h.screen()?;
+    ensure!(!screen.contains("> Find <"), screen);
+    ensure!(h.app.state.get_active_book().unwrap().name == "rust");
+    ensure!(h.app.state.get_active_page().unwrap().id == 3);
+    ensure!(h.app.state.mode == Mode::Page);
+    Ok(())
+}
+
+type Test = fn() -> Result<()>;
+
+const TESTS: &[(&str, Test)] = &[
+    (
+        "browses_books_pages_and_content",
+        browses_books_pages_and_content,
+    ),
+    (
+        "adds_a_note_to_the_active_book",
+        adds_a_note_to_the_active_book,
+    ),
+    (
+        "shows_note_counts_and_totals_in_the_books_pane",
+        shows_note_counts_and_totals_in_the_books_pane,
+    ),
+    (
+        "keeps_the_editor_open_when_saving_fails",
+        keeps_the_editor_open_when_saving_fails,
+    ),
+    ("edits_the_active_note", edits_the_active_note),
+    (
+        "discards_unsaved_edits_on_double_escape",
+        discards_unsaved_edits_on_double_escape,
+    ),
+    (
+        "edits_the_active_note_externally",
+        edits_the_active_note_externally,
+    ),
+    (
+        "creates_a_book_with_its_first_note",
+        creates_a_book_with_its_first_note,
+    ),
+    (
+        "renders_markdown_and_toggles_raw_view",
+        renders_markdown_and_toggles_raw_view,
+    ),
+    (
+        "focuses_and_scrolls_the_content_pane",
+        focuses_and_scrolls_the_content_pane,
+    ),
+    (
+        "clicks_and_scrolls_with_the_mouse",
+        clicks_and_scrolls_with_the_mouse,
+    ),
+    (
+        "moves_the_active_note_to_another_book",
+        moves_the_active_note_to_another_book,
+    ),
+    (
+        "exports_moves_and_deletes_marked_notes",
+        exports_moves_and_deletes_marked_notes,
+    ),
+    (
+        "counts_the_notes_a_bulk_delete_does_not_list",
+        counts_the_notes_a_bulk_delete_does_not_list,
+    ),
+    (
+        "sorts_pages_keeping_the_selected_note",
+        sorts_pages_keeping_the_selected_note,
+    ),
+    (
+        "filters_books_and_pages_as_you_type",
+        filters_books_and_pages_as_you_type,
+    ),
+    (
+        "moves_and_jumps_to_books_hidden_by_the_filter",
+        moves_and_jumps_to_books_hidden_by_the_filter,
+    ),
+    ("renames_the_active_book", renames_the_active_book),
+    ("rejects_invalid_book_names", rejects_invalid_book_names),
+    ("deletes_the_active_note", deletes_the_active_note),
+    (
+        "cancels_deletes_unless_confirmed",
+        cancels_deletes_unless_confirmed,
+    ),
+    (
+        "deletes_notes_without_confirmation_when_configured",
+        deletes_notes_without_confirmation_when_configured,
+    ),
+    (
+        "restores_deleted_notes_from_the_trash",
+        restores_deleted_notes_from_the_trash,
+    ),
+    (
+        "asks_before_purging_from_the_trash",
+        asks_before_purging_from_the_trash,
+    ),
+    ("deletes_the_active_book", deletes_the_active_book),
+    (
+        "shows_sync_failures_in_a_popup",
+        shows_sync_failures_in_a_popup,
+    ),
+    (
+        "returns_from_search_when_another_popup_replaces_it",
+        returns_from_search_when_another_popup_replaces_it,
+    ),
+    (
+        "searches_all_books_and_jumps_to_a_hit",
+        searches_all_books_and_jumps_to_a_hit,
+    ),
+    (
+        "finds_and_previews_notes_fuzzily",
+        finds_and_previews_notes_fuzzily,
+    ),
+];
+
+/// Link this executable as `dnote` and `fake-editor` into a new directory on `PATH`, and
+/// point the app's config and data at an empty directory.
+fn install_fakes(dir: &Path) -> Result<()> {
+    let exe = env::current_exe()?;
+    for name in ["dnote", "fake-editor"] {
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&exe, dir.join(name))?;
+        #[cfg(not(unix))]
+        std::fs::copy(
+            &exe,
+            dir.join(name).with_extension(env::consts::EXE_EXTENSION),
+        )?;
+    }
+    let path = env::var_os("PATH").unwrap_or_default();
+    let paths = std::iter::once(dir.to_path_buf()).chain(env::split_paths(&path));
+    // Still single-threaded here, no other thread reads the environment
+    env::set_var("PATH", env::join_paths(paths)?);
+    env::set_var("DNOTE_TUI_CONFIG", dir);
+    env::set_var("DNOTE_TUI_DATA", dir);
+    Ok(())
+}
+
+fn main() -> Result<ExitCode> {
+    let invoked_as = env::args_os()
+        .next()
+        .and_then(|arg| Path::new(&arg).file_stem().map(|n| n.to_owned()));
+    match invoked_as.as_ref().and_then(|n| n.to_str()) {
+        Some("dnote") => return Ok(fake_dnote::main()),
+        Some("fake-editor") => return Ok(fake_dnote::editor_main()),
+        _ => {}
+    }
+
+    let fakes = tempfile::tempdir()?;
+    install_fakes(fakes.path())?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    let _guard = runtime.enter();
+
+    let filter = env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    let (mut passed, mut failed) = (0, 0);
+    println!("\nrunning {} tests", TESTS.len());
+    for (name, test) in TESTS {
+        if filter.as_ref().is_some_and(|f| !name.contains(f.as_str())) {
+            continue;
+        }
+        match test() {
+            Ok(()) => {
+                passed += 1;
+                println!("test {name} ... ok");
+            }
+            Err(err) => {
+                failed += 1;
+                println!("test {name} ... FAILED\n{err:?}");
+            }
+        }
+    }
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    println!("\ntest result: {result}. {passed} passed; {failed} failed\n");
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}